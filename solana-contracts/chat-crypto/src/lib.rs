@@ -0,0 +1,242 @@
+//! SolaMate 端到端加密聊天的客户端辅助函数
+//!
+//! 合约只保存密文：发送方用自己的 X25519 私钥 + 对方在 EncryptionKey 账户里发布的公钥，
+//! 通过 NaCl box (X25519 + XSalsa20-Poly1305) 加密，再把 nonce、密文和双方的 key_version
+//! 一起传给 `send_message`。box 的共享密钥是对称的，所以收发双方都能解密同一条消息。
+
+use crypto_box::aead::{Aead, AeadCore, OsRng};
+use crypto_box::{Nonce, PublicKey, SalsaBox, SecretKey};
+
+/// 与合约里的常量保持一致
+pub const MESSAGE_MAX_LEN: usize = 500;
+pub const ENCRYPTION_NONCE_LEN: usize = 24;
+pub const ENCRYPTION_TAG_LEN: usize = 16;
+pub const MESSAGE_CIPHERTEXT_MAX_LEN: usize = MESSAGE_MAX_LEN + ENCRYPTION_TAG_LEN;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CryptoError {
+    /// 明文为空或超过 MESSAGE_MAX_LEN
+    InvalidPlaintextLength,
+    /// 密文长度不在合约接受的范围内
+    InvalidCiphertextLength,
+    /// 认证失败：密钥不对或密文被篡改
+    DecryptionFailed,
+}
+
+impl std::fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CryptoError::InvalidPlaintextLength => write!(f, "invalid plaintext length"),
+            CryptoError::InvalidCiphertextLength => write!(f, "invalid ciphertext length"),
+            CryptoError::DecryptionFailed => write!(f, "decryption failed"),
+        }
+    }
+}
+
+impl std::error::Error for CryptoError {}
+
+/// 用户的 X25519 密钥对，`public_key()` 就是要发布到 EncryptionKey 账户的 32 字节
+pub struct ChatKeypair {
+    secret: SecretKey,
+}
+
+impl ChatKeypair {
+    pub fn generate() -> Self {
+        Self {
+            secret: SecretKey::generate(&mut OsRng),
+        }
+    }
+
+    pub fn from_secret_bytes(bytes: [u8; 32]) -> Self {
+        Self {
+            secret: SecretKey::from(bytes),
+        }
+    }
+
+    pub fn secret_bytes(&self) -> [u8; 32] {
+        self.secret.to_bytes()
+    }
+
+    pub fn public_key(&self) -> [u8; 32] {
+        *self.secret.public_key().as_bytes()
+    }
+}
+
+/// 对应 `send_message` 的 content / nonce 参数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedMessage {
+    pub nonce: [u8; ENCRYPTION_NONCE_LEN],
+    pub ciphertext: Vec<u8>,
+}
+
+/// 用自己的私钥和对方发布的公钥加密，nonce 每条消息随机生成
+pub fn encrypt(
+    sender: &ChatKeypair,
+    recipient_public_key: &[u8; 32],
+    plaintext: &[u8],
+) -> Result<EncryptedMessage, CryptoError> {
+    if plaintext.is_empty() || plaintext.len() > MESSAGE_MAX_LEN {
+        return Err(CryptoError::InvalidPlaintextLength);
+    }
+
+    let salsa_box = SalsaBox::new(&PublicKey::from(*recipient_public_key), &sender.secret);
+    let nonce = SalsaBox::generate_nonce(&mut OsRng);
+    let ciphertext = salsa_box
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| CryptoError::InvalidPlaintextLength)?;
+
+    Ok(EncryptedMessage {
+        nonce: nonce.into(),
+        ciphertext,
+    })
+}
+
+/// 用自己的私钥和对方的公钥解密；发送方解密自己发出的消息时传入接收方的公钥
+pub fn decrypt(
+    own: &ChatKeypair,
+    peer_public_key: &[u8; 32],
+    message: &EncryptedMessage,
+) -> Result<Vec<u8>, CryptoError> {
+    if message.ciphertext.len() <= ENCRYPTION_TAG_LEN
+        || message.ciphertext.len() > MESSAGE_CIPHERTEXT_MAX_LEN
+    {
+        return Err(CryptoError::InvalidCiphertextLength);
+    }
+
+    let salsa_box = SalsaBox::new(&PublicKey::from(*peer_public_key), &own.secret);
+    salsa_box
+        .decrypt(
+            Nonce::from_slice(&message.nonce),
+            message.ciphertext.as_slice(),
+        )
+        .map_err(|_| CryptoError::DecryptionFailed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_for_both_participants() {
+        let alice = ChatKeypair::generate();
+        let bob = ChatKeypair::generate();
+        let plaintext = "gm, lunch today? 🍜".as_bytes();
+
+        let message = encrypt(&alice, &bob.public_key(), plaintext).unwrap();
+        assert_eq!(
+            message.ciphertext.len(),
+            plaintext.len() + ENCRYPTION_TAG_LEN
+        );
+        assert_ne!(message.ciphertext[..plaintext.len()], *plaintext);
+
+        // 接收方和发送方都能解密
+        assert_eq!(
+            decrypt(&bob, &alice.public_key(), &message).unwrap(),
+            plaintext
+        );
+        assert_eq!(
+            decrypt(&alice, &bob.public_key(), &message).unwrap(),
+            plaintext
+        );
+    }
+
+    #[test]
+    fn keypair_restores_from_secret_bytes() {
+        let alice = ChatKeypair::generate();
+        let bob = ChatKeypair::generate();
+        let restored = ChatKeypair::from_secret_bytes(alice.secret_bytes());
+        assert_eq!(restored.public_key(), alice.public_key());
+
+        let message = encrypt(&bob, &alice.public_key(), b"hello").unwrap();
+        assert_eq!(
+            decrypt(&restored, &bob.public_key(), &message).unwrap(),
+            b"hello"
+        );
+    }
+
+    #[test]
+    fn nonce_is_fresh_per_message() {
+        let alice = ChatKeypair::generate();
+        let bob = ChatKeypair::generate();
+
+        let first = encrypt(&alice, &bob.public_key(), b"same").unwrap();
+        let second = encrypt(&alice, &bob.public_key(), b"same").unwrap();
+        assert_ne!(first.nonce, second.nonce);
+        assert_ne!(first.ciphertext, second.ciphertext);
+    }
+
+    #[test]
+    fn third_party_and_rotated_keys_cannot_decrypt() {
+        let alice = ChatKeypair::generate();
+        let bob = ChatKeypair::generate();
+        let eve = ChatKeypair::generate();
+        let message = encrypt(&alice, &bob.public_key(), b"secret").unwrap();
+
+        assert_eq!(
+            decrypt(&eve, &alice.public_key(), &message),
+            Err(CryptoError::DecryptionFailed)
+        );
+
+        // bob 轮换密钥后，旧版本的消息用新私钥解不开，这也是合约检查 key_version 的原因
+        let rotated_bob = ChatKeypair::generate();
+        assert_eq!(
+            decrypt(&rotated_bob, &alice.public_key(), &message),
+            Err(CryptoError::DecryptionFailed)
+        );
+    }
+
+    #[test]
+    fn tampered_ciphertext_or_nonce_is_rejected() {
+        let alice = ChatKeypair::generate();
+        let bob = ChatKeypair::generate();
+        let message = encrypt(&alice, &bob.public_key(), b"pay me back").unwrap();
+
+        let mut tampered = message.clone();
+        tampered.ciphertext[0] ^= 1;
+        assert_eq!(
+            decrypt(&bob, &alice.public_key(), &tampered),
+            Err(CryptoError::DecryptionFailed)
+        );
+
+        let mut tampered = message;
+        tampered.nonce[0] ^= 1;
+        assert_eq!(
+            decrypt(&bob, &alice.public_key(), &tampered),
+            Err(CryptoError::DecryptionFailed)
+        );
+    }
+
+    #[test]
+    fn lengths_match_program_limits() {
+        let alice = ChatKeypair::generate();
+        let bob = ChatKeypair::generate();
+
+        let longest = vec![b'x'; MESSAGE_MAX_LEN];
+        let message = encrypt(&alice, &bob.public_key(), &longest).unwrap();
+        assert_eq!(message.ciphertext.len(), MESSAGE_CIPHERTEXT_MAX_LEN);
+        assert_eq!(
+            decrypt(&bob, &alice.public_key(), &message).unwrap(),
+            longest
+        );
+
+        assert_eq!(
+            encrypt(&alice, &bob.public_key(), &[]),
+            Err(CryptoError::InvalidPlaintextLength)
+        );
+        assert_eq!(
+            encrypt(&alice, &bob.public_key(), &vec![b'x'; MESSAGE_MAX_LEN + 1]),
+            Err(CryptoError::InvalidPlaintextLength)
+        );
+        assert_eq!(
+            decrypt(
+                &bob,
+                &alice.public_key(),
+                &EncryptedMessage {
+                    nonce: [0; ENCRYPTION_NONCE_LEN],
+                    ciphertext: vec![0; ENCRYPTION_TAG_LEN],
+                }
+            ),
+            Err(CryptoError::InvalidCiphertextLength)
+        );
+    }
+}
@@ -0,0 +1,40 @@
+//! 各个 program-test 共用的 processor 和账户构造
+
+use anchor_lang::AccountSerialize;
+use solana_program_test::BanksClientError;
+use solana_sdk::account::Account;
+use solana_sdk::account_info::AccountInfo;
+use solana_sdk::entrypoint::ProgramResult;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::TransactionError;
+
+pub fn process_solamate(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    // Anchor 的 entry 要求 &'info [AccountInfo<'info>]
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    solamate_program::entry(program_id, accounts, data)
+}
+
+/// 按账户的 LEN 序列化并补零，owner 为 solamate 程序
+pub fn program_account<T: AccountSerialize>(account: &T, len: usize, lamports: u64) -> Account {
+    let mut data = Vec::with_capacity(len);
+    account.try_serialize(&mut data).unwrap();
+    data.resize(len, 0);
+    Account {
+        lamports,
+        data,
+        owner: solamate_program::ID,
+        ..Account::default()
+    }
+}
+
+pub fn custom_error(error: BanksClientError) -> Option<u32> {
+    match error.unwrap() {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => Some(code),
+        _ => None,
+    }
+}
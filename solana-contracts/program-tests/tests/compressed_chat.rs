@@ -0,0 +1,370 @@
+//! 压缩聊天记录的 program-test 用例
+//!
+//! SPL Account Compression / Noop 没有本地 .so，这里用原生 processor 代替：
+//! compression 程序按真实账户布局（56 字节 header + ConcurrentMerkleTree<20, 64>）
+//! 调用 spl-concurrent-merkle-tree 初始化和追加叶子，noop 程序直接返回。
+
+mod common;
+
+use anchor_lang::solana_program::keccak;
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::account::Account;
+use solana_sdk::account_info::AccountInfo;
+use solana_sdk::entrypoint::ProgramResult;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::program_error::ProgramError;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_instruction;
+use solana_sdk::sysvar::clock::Clock;
+use solana_sdk::transaction::Transaction;
+use spl_concurrent_merkle_tree::concurrent_merkle_tree::ConcurrentMerkleTree;
+use spl_concurrent_merkle_tree::hash::recompute;
+use spl_concurrent_merkle_tree::node::empty_node;
+
+use common::{custom_error, process_solamate, program_account};
+use solamate_program::compression::{
+    tree_authority, CompressedChatMessage, ACCOUNT_COMPRESSION_ID, CHAT_TREE_ACCOUNT_LEN,
+    CHAT_TREE_MAX_BUFFER_SIZE, CHAT_TREE_MAX_DEPTH, NOOP_ID,
+};
+use solamate_program::{ChatRoom, ErrorCode, ACCOUNT_RESERVED_BYTES, ACCOUNT_VERSION};
+
+type ChatTree = ConcurrentMerkleTree<CHAT_TREE_MAX_DEPTH, CHAT_TREE_MAX_BUFFER_SIZE>;
+
+const TREE_HEADER_LEN: usize = 56;
+const INIT_EMPTY_MERKLE_TREE_DISCRIMINATOR: [u8; 8] = [191, 11, 119, 7, 180, 107, 220, 110];
+const APPEND_DISCRIMINATOR: [u8; 8] = [149, 120, 18, 222, 236, 225, 88, 203];
+
+fn process_noop(_: &Pubkey, _: &[AccountInfo], _: &[u8]) -> ProgramResult {
+    Ok(())
+}
+
+/// 账户顺序与 solamate_program::compression 中的 CPI 一致：merkle_tree, authority, noop
+fn process_compression(_: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let (tree_info, authority) = match accounts {
+        [tree, authority, ..] => (tree, authority),
+        _ => return Err(ProgramError::NotEnoughAccountKeys),
+    };
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let mut account_data = tree_info.try_borrow_mut_data()?;
+    let (header, body) = account_data.split_at_mut(TREE_HEADER_LEN);
+    let mut tree: ChatTree = bytemuck::pod_read_unaligned(body);
+
+    match (&data[..8], &data[8..]) {
+        (discriminator, args) if discriminator == INIT_EMPTY_MERKLE_TREE_DISCRIMINATOR => {
+            let max_depth = u32::from_le_bytes(args[0..4].try_into().unwrap());
+            let max_buffer_size = u32::from_le_bytes(args[4..8].try_into().unwrap());
+            if max_depth as usize != CHAT_TREE_MAX_DEPTH
+                || max_buffer_size as usize != CHAT_TREE_MAX_BUFFER_SIZE
+                || header[0] != 0
+            {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+
+            // account_type = ConcurrentMerkleTree, header version = V1
+            header[0] = 1;
+            header[1] = 0;
+            header[2..6].copy_from_slice(&max_buffer_size.to_le_bytes());
+            header[6..10].copy_from_slice(&max_depth.to_le_bytes());
+            header[10..42].copy_from_slice(authority.key.as_ref());
+            tree.initialize()
+                .map_err(|_| ProgramError::InvalidAccountData)?;
+        }
+        (discriminator, leaf) if discriminator == APPEND_DISCRIMINATOR => {
+            if header[10..42] != authority.key.to_bytes() {
+                return Err(ProgramError::IllegalOwner);
+            }
+            tree.append(leaf.try_into().unwrap())
+                .map_err(|_| ProgramError::InvalidAccountData)?;
+        }
+        _ => return Err(ProgramError::InvalidInstructionData),
+    }
+
+    body.copy_from_slice(bytemuck::bytes_of(&tree));
+    Ok(())
+}
+
+struct Harness {
+    context: ProgramTestContext,
+    sender: Keypair,
+    peer: Pubkey,
+    chat_room: Pubkey,
+}
+
+/// 预置一个 ChatRoom 账户；sender 单独建号，避免依赖 payer 的地址顺序
+async fn setup() -> Harness {
+    let mut program_test = ProgramTest::new(
+        "solamate_program",
+        solamate_program::ID,
+        processor!(process_solamate),
+    );
+    program_test.add_program(
+        "spl_account_compression",
+        ACCOUNT_COMPRESSION_ID,
+        processor!(process_compression),
+    );
+    program_test.add_program("spl_noop", NOOP_ID, processor!(process_noop));
+
+    let sender = Keypair::new();
+    let peer = Pubkey::new_unique();
+    let (user_a, user_b) = if sender.pubkey() < peer {
+        (sender.pubkey(), peer)
+    } else {
+        (peer, sender.pubkey())
+    };
+    let (chat_room, bump) = Pubkey::find_program_address(
+        &[b"chat_room", user_a.as_ref(), user_b.as_ref()],
+        &solamate_program::ID,
+    );
+
+    let chat_room_account = ChatRoom {
+        user_a,
+        user_b,
+        message_count: 3,
+        last_message_at: 0,
+        bump,
+        version: ACCOUNT_VERSION,
+        page_count: 0,
+        oldest_page: 0,
+        retained_pages: 0,
+        compressed: false,
+        tree_root: [0; 32],
+        tree_leaf_count: 0,
+        reserved: [0; ACCOUNT_RESERVED_BYTES - 51],
+    };
+    program_test.add_account(
+        chat_room,
+        program_account(&chat_room_account, ChatRoom::LEN, 1_000_000_000),
+    );
+    program_test.add_account(
+        sender.pubkey(),
+        Account {
+            lamports: 1_000_000_000,
+            ..Account::default()
+        },
+    );
+
+    Harness {
+        context: program_test.start_with_context().await,
+        sender,
+        peer,
+        chat_room,
+    }
+}
+
+impl Harness {
+    async fn chat_room(&mut self) -> ChatRoom {
+        let account = self
+            .context
+            .banks_client
+            .get_account(self.chat_room)
+            .await
+            .unwrap()
+            .unwrap();
+        ChatRoom::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    async fn process(
+        &mut self,
+        instructions: &[Instruction],
+        extra_signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let mut transaction =
+            Transaction::new_with_payer(instructions, Some(&self.context.payer.pubkey()));
+        // sender 只在需要它签名的交易里签
+        let required = &transaction.message.account_keys
+            [..transaction.message.header.num_required_signatures as usize];
+        let mut signers = vec![&self.context.payer];
+        if required.contains(&self.sender.pubkey()) {
+            signers.push(&self.sender);
+        }
+        signers.extend_from_slice(extra_signers);
+        transaction.sign(&signers, blockhash);
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+    }
+
+    /// 分配 tree 账户并初始化，与客户端的做法一致：两条指令放在同一笔交易里
+    async fn initialize_chat_tree(&mut self, tree: &Keypair) -> Result<(), BanksClientError> {
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+        let instructions = [
+            system_instruction::create_account(
+                &self.context.payer.pubkey(),
+                &tree.pubkey(),
+                rent.minimum_balance(CHAT_TREE_ACCOUNT_LEN),
+                CHAT_TREE_ACCOUNT_LEN as u64,
+                &ACCOUNT_COMPRESSION_ID,
+            ),
+            Instruction {
+                program_id: solamate_program::ID,
+                accounts: solamate_program::accounts::InitializeChatTree {
+                    chat_room: self.chat_room,
+                    chat_tree: tree.pubkey(),
+                    user: self.sender.pubkey(),
+                    compression_program: ACCOUNT_COMPRESSION_ID,
+                    noop_program: NOOP_ID,
+                }
+                .to_account_metas(None),
+                data: solamate_program::instruction::InitializeChatTree {}.data(),
+            },
+        ];
+        self.process(&instructions, &[tree]).await
+    }
+
+    async fn send_compressed_message(
+        &mut self,
+        tree: Pubkey,
+        content: &str,
+    ) -> Result<(), BanksClientError> {
+        let block_pda = |blocker: &Pubkey, blocked: &Pubkey| {
+            Pubkey::find_program_address(
+                &[b"block", blocker.as_ref(), blocked.as_ref()],
+                &solamate_program::ID,
+            )
+            .0
+        };
+        let instruction = Instruction {
+            program_id: solamate_program::ID,
+            accounts: solamate_program::accounts::SendCompressedMessage {
+                chat_room: self.chat_room,
+                chat_tree: tree,
+                sender: self.sender.pubkey(),
+                sender_block: block_pda(&self.sender.pubkey(), &self.peer),
+                peer_block: block_pda(&self.peer, &self.sender.pubkey()),
+                compression_program: ACCOUNT_COMPRESSION_ID,
+                noop_program: NOOP_ID,
+                pet_state: None,
+                reward_config: None,
+                quest_progress: None,
+            }
+            .to_account_metas(None),
+            data: solamate_program::instruction::SendCompressedMessage {
+                content: content.to_string(),
+            }
+            .data(),
+        };
+        self.process(&[instruction], &[]).await
+    }
+}
+
+fn empty_tree_root() -> [u8; 32] {
+    empty_node(CHAT_TREE_MAX_DEPTH as u32)
+}
+
+#[tokio::test]
+async fn initialize_chat_tree_records_tree_and_empty_root() {
+    let mut harness = setup().await;
+    let tree = Keypair::new();
+
+    harness.initialize_chat_tree(&tree).await.unwrap();
+
+    let chat_room = harness.chat_room().await;
+    assert!(chat_room.compressed);
+    assert_eq!(chat_room.tree_root, empty_tree_root());
+    assert_eq!(chat_room.tree_leaf_count, 0);
+
+    // 树由 header 中的 authority 绑定到聊天室
+    let tree_account = harness
+        .context
+        .banks_client
+        .get_account(tree.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(tree_authority(&tree_account.data), Some(harness.chat_room));
+
+    // 同一个聊天室不能再初始化第二棵树
+    let error = harness
+        .initialize_chat_tree(&Keypair::new())
+        .await
+        .unwrap_err();
+    assert_eq!(
+        custom_error(error),
+        Some(ErrorCode::ChatTreeAlreadyInitialized.into())
+    );
+}
+
+#[tokio::test]
+async fn send_compressed_message_appends_leaf_hash_to_tree_root() {
+    let mut harness = setup().await;
+    let tree = Keypair::new();
+    harness.initialize_chat_tree(&tree).await.unwrap();
+
+    harness
+        .send_compressed_message(tree.pubkey(), "gm from the tree")
+        .await
+        .unwrap();
+    let clock: Clock = harness.context.banks_client.get_sysvar().await.unwrap();
+
+    let leaf = CompressedChatMessage {
+        chat_room: harness.chat_room,
+        sender: harness.sender.pubkey(),
+        message_index: 3,
+        leaf_index: 0,
+        timestamp: clock.unix_timestamp,
+        content: b"gm from the tree".to_vec(),
+    }
+    .leaf_hash();
+    assert_eq!(
+        leaf,
+        keccak::hashv(&[
+            harness.chat_room.as_ref(),
+            harness.sender.pubkey().as_ref(),
+            &3u64.to_le_bytes(),
+            &clock.unix_timestamp.to_le_bytes(),
+            b"gm from the tree",
+        ])
+        .to_bytes()
+    );
+
+    // 第一片叶子的 proof 全是空节点
+    let proof: Vec<[u8; 32]> = (0..CHAT_TREE_MAX_DEPTH as u32).map(empty_node).collect();
+    let expected_root = recompute(leaf, &proof, 0);
+
+    let chat_room = harness.chat_room().await;
+    assert_eq!(chat_room.tree_root, expected_root);
+    assert_eq!(chat_room.tree_leaf_count, 1);
+    assert_eq!(chat_room.message_count, 4);
+    assert_eq!(chat_room.last_message_at, clock.unix_timestamp);
+}
+
+#[tokio::test]
+async fn send_compressed_message_rejects_other_tree() {
+    let mut harness = setup().await;
+    let tree = Keypair::new();
+    harness.initialize_chat_tree(&tree).await.unwrap();
+
+    // 另一个聊天室的树：owner 正确，但不是 ChatRoom.tree
+    let other = setup_other_tree(&mut harness).await;
+    let error = harness
+        .send_compressed_message(other, "wrong tree")
+        .await
+        .unwrap_err();
+    assert_eq!(custom_error(error), Some(ErrorCode::InvalidChatTree.into()));
+
+    let chat_room = harness.chat_room().await;
+    assert_eq!(chat_room.tree_root, empty_tree_root());
+    assert_eq!(chat_room.tree_leaf_count, 0);
+}
+
+/// 分配一个属于 compression 程序、但未登记在聊天室上的 tree 账户
+async fn setup_other_tree(harness: &mut Harness) -> Pubkey {
+    let other = Keypair::new();
+    let rent = harness.context.banks_client.get_rent().await.unwrap();
+    let instruction = system_instruction::create_account(
+        &harness.context.payer.pubkey(),
+        &other.pubkey(),
+        rent.minimum_balance(CHAT_TREE_ACCOUNT_LEN),
+        CHAT_TREE_ACCOUNT_LEN as u64,
+        &ACCOUNT_COMPRESSION_ID,
+    );
+    harness.process(&[instruction], &[&other]).await.unwrap();
+    other.pubkey()
+}
@@ -0,0 +1,230 @@
+//! remove_friend 的 program-test 用例：只有好友关系双方能删除，租金退还给请求方
+
+mod common;
+
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::account::Account;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+
+use common::{custom_error, process_solamate, program_account};
+use solamate_program::{
+    ErrorCode, Friendship, FriendshipStatus, UserProfile, ACCOUNT_RESERVED_BYTES, ACCOUNT_VERSION,
+};
+
+const FRIENDSHIP_LAMPORTS: u64 = 10_000_000;
+
+struct Harness {
+    context: ProgramTestContext,
+    requester: Keypair,
+    recipient: Keypair,
+    friendship: Pubkey,
+    user_a_profile: Pubkey,
+    user_b_profile: Pubkey,
+}
+
+fn user_profile(owner: Pubkey, bump: u8, friend_count: u32, pending: u32) -> UserProfile {
+    UserProfile {
+        owner,
+        username: "user".to_string(),
+        avatar: "1.png".to_string(),
+        pet_id: 1,
+        friend_count,
+        created_at: 0,
+        bump,
+        version: ACCOUNT_VERSION,
+        pending_request_count: pending,
+        reserved: [0; ACCOUNT_RESERVED_BYTES - 4],
+    }
+}
+
+/// 预置 requester -> recipient 的好友关系（未写入好友分页）和双方的 UserProfile
+async fn setup(status: FriendshipStatus) -> Harness {
+    let mut program_test = ProgramTest::new(
+        "solamate_program",
+        solamate_program::ID,
+        processor!(process_solamate),
+    );
+
+    let requester = Keypair::new();
+    let recipient = Keypair::new();
+    let (user_a, user_b) = if requester.pubkey() < recipient.pubkey() {
+        (requester.pubkey(), recipient.pubkey())
+    } else {
+        (recipient.pubkey(), requester.pubkey())
+    };
+    let (friendship, bump) = Pubkey::find_program_address(
+        &[b"friendship", user_a.as_ref(), user_b.as_ref()],
+        &solamate_program::ID,
+    );
+
+    // 已接受的好友计入 friend_count，待处理的请求只计入 pending_request_count
+    let (friend_count, pending) = match status {
+        FriendshipStatus::Accepted => (1, 0),
+        FriendshipStatus::Pending => (2, 1),
+    };
+    let friendship_account = Friendship {
+        user_a,
+        user_b,
+        requester: requester.pubkey(),
+        status,
+        created_at: 0,
+        bump,
+        version: ACCOUNT_VERSION,
+        indexed: false,
+        user_a_page: 0,
+        user_b_page: 0,
+        reserved: [0; ACCOUNT_RESERVED_BYTES - 9],
+    };
+    program_test.add_account(
+        friendship,
+        program_account(&friendship_account, Friendship::LEN, FRIENDSHIP_LAMPORTS),
+    );
+
+    let mut profiles = Vec::new();
+    for owner in [user_a, user_b] {
+        let (profile, bump) =
+            Pubkey::find_program_address(&[b"user_profile", owner.as_ref()], &solamate_program::ID);
+        program_test.add_account(
+            profile,
+            program_account(
+                &user_profile(owner, bump, friend_count, pending),
+                UserProfile::LEN,
+                1_000_000_000,
+            ),
+        );
+        profiles.push(profile);
+    }
+
+    program_test.add_account(
+        requester.pubkey(),
+        Account {
+            lamports: 1_000_000_000,
+            ..Account::default()
+        },
+    );
+
+    Harness {
+        context: program_test.start_with_context().await,
+        requester,
+        recipient,
+        friendship,
+        user_a_profile: profiles[0],
+        user_b_profile: profiles[1],
+    }
+}
+
+impl Harness {
+    async fn remove_friend(&mut self, user: &Keypair) -> Result<(), BanksClientError> {
+        let instruction = Instruction {
+            program_id: solamate_program::ID,
+            accounts: solamate_program::accounts::RemoveFriend {
+                friendship: self.friendship,
+                requester: self.requester.pubkey(),
+                user_a_profile: self.user_a_profile,
+                user_b_profile: self.user_b_profile,
+                user: user.pubkey(),
+                user_a_friend_index: None,
+                user_a_friend_page: None,
+                user_b_friend_index: None,
+                user_b_friend_page: None,
+            }
+            .to_account_metas(None),
+            data: solamate_program::instruction::RemoveFriend {}.data(),
+        };
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&self.context.payer.pubkey()),
+            &[&self.context.payer, user],
+            blockhash,
+        );
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+    }
+
+    async fn lamports(&mut self, address: Pubkey) -> u64 {
+        self.context
+            .banks_client
+            .get_balance(address)
+            .await
+            .unwrap()
+    }
+
+    async fn profiles(&mut self) -> Vec<UserProfile> {
+        let mut profiles = Vec::new();
+        for address in [self.user_a_profile, self.user_b_profile] {
+            let account = self
+                .context
+                .banks_client
+                .get_account(address)
+                .await
+                .unwrap()
+                .unwrap();
+            profiles.push(UserProfile::try_deserialize(&mut account.data.as_slice()).unwrap());
+        }
+        profiles
+    }
+}
+
+#[tokio::test]
+async fn outsider_cannot_remove_friendship() {
+    let mut harness = setup(FriendshipStatus::Accepted).await;
+
+    let error = harness.remove_friend(&Keypair::new()).await.unwrap_err();
+    assert_eq!(custom_error(error), Some(ErrorCode::Unauthorized.into()));
+
+    // 好友关系和好友数都保持不变
+    assert_eq!(
+        harness.lamports(harness.friendship).await,
+        FRIENDSHIP_LAMPORTS
+    );
+    for profile in harness.profiles().await {
+        assert_eq!(profile.friend_count, 1);
+    }
+}
+
+#[tokio::test]
+async fn removing_accepted_friendship_refunds_requester_and_decrements_friend_count() {
+    let mut harness = setup(FriendshipStatus::Accepted).await;
+    let requester_before = harness.lamports(harness.requester.pubkey()).await;
+
+    // 由非请求方删除，租金仍退还给请求方
+    let recipient = harness.recipient.insecure_clone();
+    harness.remove_friend(&recipient).await.unwrap();
+
+    assert_eq!(harness.lamports(harness.friendship).await, 0);
+    assert_eq!(
+        harness.lamports(harness.requester.pubkey()).await,
+        requester_before + FRIENDSHIP_LAMPORTS
+    );
+    for profile in harness.profiles().await {
+        assert_eq!(profile.friend_count, 0);
+        assert_eq!(profile.pending_request_count, 0);
+    }
+}
+
+#[tokio::test]
+async fn removing_pending_request_keeps_friend_count() {
+    let mut harness = setup(FriendshipStatus::Pending).await;
+    let requester_before = harness.lamports(harness.requester.pubkey()).await;
+
+    let requester = harness.requester.insecure_clone();
+    harness.remove_friend(&requester).await.unwrap();
+
+    assert_eq!(harness.lamports(harness.friendship).await, 0);
+    // requester 只签名，手续费由 payer 支付
+    assert_eq!(
+        harness.lamports(harness.requester.pubkey()).await,
+        requester_before + FRIENDSHIP_LAMPORTS
+    );
+    for profile in harness.profiles().await {
+        assert_eq!(profile.friend_count, 2);
+        assert_eq!(profile.pending_request_count, 0);
+    }
+}
@@ -0,0 +1,140 @@
+//! select_pet 的 program-test 用例：已领养宠物时必须先 release_pet
+
+mod common;
+
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::account::Account;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+
+use common::{custom_error, process_solamate, program_account};
+use solamate_program::{ErrorCode, UserProfile, ACCOUNT_RESERVED_BYTES, ACCOUNT_VERSION};
+
+struct Harness {
+    context: ProgramTestContext,
+    user: Keypair,
+    user_profile: Pubkey,
+    pet_state: Pubkey,
+}
+
+/// 预置 UserProfile；has_pet 时在宠物 PDA 上放一个已初始化的账户
+async fn setup(has_pet: bool) -> Harness {
+    let mut program_test = ProgramTest::new(
+        "solamate_program",
+        solamate_program::ID,
+        processor!(process_solamate),
+    );
+
+    let user = Keypair::new();
+    let (user_profile, bump) = Pubkey::find_program_address(
+        &[b"user_profile", user.pubkey().as_ref()],
+        &solamate_program::ID,
+    );
+    let (pet_state, _) =
+        Pubkey::find_program_address(&[b"pet", user.pubkey().as_ref()], &solamate_program::ID);
+
+    let profile = UserProfile {
+        owner: user.pubkey(),
+        username: "user".to_string(),
+        avatar: "1.png".to_string(),
+        pet_id: 1,
+        friend_count: 0,
+        created_at: 0,
+        bump,
+        version: ACCOUNT_VERSION,
+        pending_request_count: 0,
+        reserved: [0; ACCOUNT_RESERVED_BYTES - 4],
+    };
+    program_test.add_account(
+        user_profile,
+        program_account(&profile, UserProfile::LEN, 1_000_000_000),
+    );
+    program_test.add_account(
+        user.pubkey(),
+        Account {
+            lamports: 1_000_000_000,
+            ..Account::default()
+        },
+    );
+    if has_pet {
+        program_test.add_account(
+            pet_state,
+            Account {
+                lamports: 1_000_000_000,
+                data: vec![1; 8],
+                owner: solamate_program::ID,
+                ..Account::default()
+            },
+        );
+    }
+
+    Harness {
+        context: program_test.start_with_context().await,
+        user,
+        user_profile,
+        pet_state,
+    }
+}
+
+impl Harness {
+    async fn select_pet(&mut self, pet_id: u8) -> Result<(), BanksClientError> {
+        let instruction = Instruction {
+            program_id: solamate_program::ID,
+            accounts: solamate_program::accounts::SelectPet {
+                user_profile: self.user_profile,
+                user: self.user.pubkey(),
+                owner: self.user.pubkey(),
+                pet_state: self.pet_state,
+            }
+            .to_account_metas(None),
+            data: solamate_program::instruction::SelectPet { pet_id }.data(),
+        };
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&self.context.payer.pubkey()),
+            &[&self.context.payer, &self.user],
+            blockhash,
+        );
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+    }
+
+    async fn pet_id(&mut self) -> u8 {
+        let account = self
+            .context
+            .banks_client
+            .get_account(self.user_profile)
+            .await
+            .unwrap()
+            .unwrap();
+        UserProfile::try_deserialize(&mut account.data.as_slice())
+            .unwrap()
+            .pet_id
+    }
+}
+
+#[tokio::test]
+async fn select_pet_before_adopting() {
+    let mut harness = setup(false).await;
+
+    harness.select_pet(3).await.unwrap();
+    assert_eq!(harness.pet_id().await, 3);
+}
+
+#[tokio::test]
+async fn select_pet_rejected_while_pet_exists() {
+    let mut harness = setup(true).await;
+
+    let error = harness.select_pet(3).await.unwrap_err();
+    assert_eq!(
+        custom_error(error),
+        Some(ErrorCode::PetAlreadyAdopted.into())
+    );
+    assert_eq!(harness.pet_id().await, 1);
+}
@@ -1,4 +1,16 @@
 use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::metadata::mpl_token_metadata::types::DataV2;
+use anchor_spl::metadata::{
+    create_master_edition_v3, create_metadata_accounts_v3, update_metadata_accounts_v2,
+    CreateMasterEditionV3, CreateMetadataAccountsV3, Metadata, UpdateMetadataAccountsV2,
+};
+use anchor_spl::token::{mint_to, Mint, MintTo, Token, TokenAccount};
+use validation::{
+    AVATAR_MAX_LEN, DESCRIPTION_MAX_LEN, IPFS_HASH_MAX_LEN, MESSAGE_MAX_LEN, PROFILE_BIO_MAX_BYTES,
+    PROFILE_LINK_MAX_LEN, PROFILE_MAX_LINKS, PROFILE_URI_MAX_LEN, TITLE_MAX_LEN,
+    TX_SIGNATURE_MAX_LEN, USERNAME_MAX_BYTES,
+};
 
 declare_id!("H5Zs6GVUnjZSTuMwJzBTRRtvFrS1gfSNYtVWzFaxCNiD");
 
@@ -16,11 +28,8 @@ pub mod solamate_program {
         username: String,
         avatar: String,
     ) -> Result<()> {
-        require!(
-            username.len() >= 3 && username.len() <= 20,
-            ErrorCode::InvalidUsername
-        );
-        require!(avatar.len() <= 10, ErrorCode::InvalidAvatar);
+        validation::validate_username(&username)?;
+        require!(avatar.len() <= AVATAR_MAX_LEN, ErrorCode::InvalidAvatar);
 
         let profile = &mut ctx.accounts.user_profile;
         profile.owner = ctx.accounts.user.key();
@@ -28,8 +37,24 @@ pub mod solamate_program {
         profile.avatar = avatar;
         profile.pet_id = 0;
         profile.friend_count = 0;
+        profile.pending_request_count = 0;
         profile.created_at = Clock::get()?.unix_timestamp;
         profile.bump = ctx.bumps.user_profile;
+        profile.version = ACCOUNT_VERSION;
+
+        claim_username(
+            &mut ctx.accounts.username_record,
+            profile.owner,
+            &username,
+            ctx.bumps.username_record,
+        )?;
+
+        emit!(ProfileCreated {
+            owner: profile.owner,
+            username: username.clone(),
+            avatar: profile.avatar.clone(),
+            created_at: profile.created_at,
+        });
 
         msg!(
             "User profile created for: {} with username: {}",
@@ -45,16 +70,25 @@ pub mod solamate_program {
         username: String,
         avatar: String,
     ) -> Result<()> {
-        require!(
-            username.len() >= 3 && username.len() <= 20,
-            ErrorCode::InvalidUsername
-        );
-        require!(avatar.len() <= 10, ErrorCode::InvalidAvatar);
+        validation::validate_username(&username)?;
+        require!(avatar.len() <= AVATAR_MAX_LEN, ErrorCode::InvalidAvatar);
 
         let profile = &mut ctx.accounts.user_profile;
+        // 只允许修改大小写；换名需要走 change_username 以释放旧名字
+        require!(
+            validation::normalize_username(&username)
+                == validation::normalize_username(&profile.username),
+            ErrorCode::UsernameChangeRequiresRename
+        );
         profile.username = username.clone();
         profile.avatar = avatar;
 
+        emit!(ProfileUpdated {
+            owner: profile.owner,
+            username: username.clone(),
+            avatar: profile.avatar.clone(),
+        });
+
         msg!(
             "User profile updated for: {} with username: {}",
             profile.owner,
@@ -63,12 +97,181 @@ pub mod solamate_program {
         Ok(())
     }
 
+    /// 更换用户名：释放旧名字并占用新名字
+    pub fn change_username(ctx: Context<ChangeUsername>, new_username: String) -> Result<()> {
+        validation::validate_username(&new_username)?;
+
+        let profile = &mut ctx.accounts.user_profile;
+        require!(
+            validation::normalize_username(&new_username)
+                != validation::normalize_username(&profile.username),
+            ErrorCode::UsernameUnchanged
+        );
+
+        // 早于注册表创建的 Profile 没有旧记录，直接跳过释放
+        let old_record = ctx.accounts.old_username_record.to_account_info();
+        if !old_record.data_is_empty() {
+            let record = UsernameRecord::try_deserialize(&mut &old_record.try_borrow_data()?[..])?;
+            require!(record.owner == profile.owner, ErrorCode::Unauthorized);
+            close_pda(&old_record, &ctx.accounts.user.to_account_info())?;
+        }
+
+        claim_username(
+            &mut ctx.accounts.new_username_record,
+            profile.owner,
+            &new_username,
+            ctx.bumps.new_username_record,
+        )?;
+
+        let old_username = std::mem::replace(&mut profile.username, new_username);
+
+        emit!(UsernameChanged {
+            owner: profile.owner,
+            old_username: old_username.clone(),
+            new_username: profile.username.clone(),
+        });
+
+        msg!(
+            "User {} renamed from {} to {}",
+            profile.owner,
+            old_username,
+            profile.username
+        );
+        Ok(())
+    }
+
+    /// 更新 Profile 扩展资料（简介、头像 URI、社交链接），首次调用时创建
+    pub fn update_profile_details(
+        ctx: Context<UpdateProfileDetails>,
+        bio: String,
+        image_uri: String,
+        links: Vec<String>,
+    ) -> Result<()> {
+        validation::validate_bio(&bio)?;
+        require!(
+            image_uri.is_empty() || validation::is_valid_image_uri(&image_uri),
+            ErrorCode::InvalidImageUri
+        );
+        require!(links.len() <= PROFILE_MAX_LINKS, ErrorCode::TooManyLinks);
+        require!(
+            links.iter().all(|link| validation::is_valid_link(link)),
+            ErrorCode::InvalidLink
+        );
+
+        let details = &mut ctx.accounts.profile_details;
+        details.owner = ctx.accounts.user.key();
+        details.bio = bio;
+        details.image_uri = image_uri;
+        details.links = links;
+        details.updated_at = Clock::get()?.unix_timestamp;
+        details.bump = ctx.bumps.profile_details;
+        details.version = ACCOUNT_VERSION;
+
+        emit!(ProfileDetailsUpdated {
+            owner: details.owner,
+            image_uri: details.image_uri.clone(),
+            link_count: details.links.len() as u8,
+            updated_at: details.updated_at,
+        });
+
+        msg!("Profile details updated for: {}", details.owner);
+        Ok(())
+    }
+
+    /// 注销 Profile：退还租金、释放用户名，并一并关闭未铸造的宠物、任务进度、扩展资料、加密公钥和好友分页
+    /// （好友分页按 0..page_count 顺序放在 remaining_accounts 中）
+    pub fn close_profile<'info>(
+        ctx: Context<'_, '_, '_, 'info, CloseProfile<'info>>,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.expense_stats.data_is_empty(),
+            ErrorCode::ProfileHasExpenseStats
+        );
+
+        let user = ctx.accounts.user.to_account_info();
+
+        let pet = ctx.accounts.pet_state.to_account_info();
+        if !pet.data_is_empty() {
+            let pet_state = PetState::try_deserialize(&mut &pet.try_borrow_data()?[..])?;
+            require!(
+                pet_state.nft_mint == Pubkey::default(),
+                ErrorCode::ProfileHasPetNft
+            );
+            close_pda(&pet, &user)?;
+        }
+
+        let quests = ctx.accounts.quest_progress.to_account_info();
+        if !quests.data_is_empty() {
+            close_pda(&quests, &user)?;
+        }
+
+        let details = ctx.accounts.profile_details.to_account_info();
+        if !details.data_is_empty() {
+            close_pda(&details, &user)?;
+        }
+
+        let encryption_key = ctx.accounts.encryption_key.to_account_info();
+        if !encryption_key.data_is_empty() {
+            close_pda(&encryption_key, &user)?;
+        }
+
+        // 好友数已经为 0，分页里不再有好友，必须全部传入一起关闭
+        let friend_index = ctx.accounts.friend_index.to_account_info();
+        if !friend_index.data_is_empty() {
+            let index = FriendIndex::try_deserialize(&mut &friend_index.try_borrow_data()?[..])?;
+            require!(
+                ctx.remaining_accounts.len() == index.page_count as usize,
+                ErrorCode::FriendPagesMissing
+            );
+            for (page_no, page) in ctx.remaining_accounts.iter().enumerate() {
+                let (expected, _) = Pubkey::find_program_address(
+                    &[
+                        b"friend_page",
+                        user.key().as_ref(),
+                        &(page_no as u32).to_le_bytes(),
+                    ],
+                    ctx.program_id,
+                );
+                require_keys_eq!(page.key(), expected, ErrorCode::InvalidFriendPage);
+                if !page.data_is_empty() {
+                    close_pda(page, &user)?;
+                }
+            }
+            close_pda(&friend_index, &user)?;
+        }
+
+        // 早于注册表的 Profile 可能没有记录，也可能同名记录属于别人
+        let record = ctx.accounts.username_record.to_account_info();
+        if !record.data_is_empty() {
+            let username_record =
+                UsernameRecord::try_deserialize(&mut &record.try_borrow_data()?[..])?;
+            if username_record.owner == user.key() {
+                close_pda(&record, &user)?;
+            }
+        }
+
+        emit!(ProfileClosed { owner: user.key() });
+
+        msg!("User profile closed for: {}", user.key());
+        Ok(())
+    }
+
     pub fn select_pet(ctx: Context<SelectPet>, pet_id: u8) -> Result<()> {
-        require!(pet_id >= 1 && pet_id <= 10, ErrorCode::InvalidPetId);
+        require!((1..=10).contains(&pet_id), ErrorCode::InvalidPetId);
+        // 已领养的宠物由 PetState 决定种类，必须先 release_pet 才能换
+        require!(
+            ctx.accounts.pet_state.data_is_empty(),
+            ErrorCode::PetAlreadyAdopted
+        );
 
         let profile = &mut ctx.accounts.user_profile;
         profile.pet_id = pet_id;
 
+        emit!(PetSelected {
+            owner: profile.owner,
+            pet_id,
+        });
+
         msg!("User {} selected pet #{}", profile.owner, pet_id);
         Ok(())
     }
@@ -87,19 +290,40 @@ pub mod solamate_program {
             (sender == user_a && friend == user_b) || (sender == user_b && friend == user_a),
             ErrorCode::InvalidKeyOrder
         );
+        require!(
+            ctx.accounts.sender_block.data_is_empty() && ctx.accounts.friend_block.data_is_empty(),
+            ErrorCode::UserBlocked
+        );
 
         friendship.user_a = user_a;
         friendship.user_b = user_b;
         friendship.requester = sender;
         friendship.status = FriendshipStatus::Pending;
         friendship.created_at = Clock::get()?.unix_timestamp;
+        friendship.indexed = false;
         friendship.bump = ctx.bumps.friendship;
+        friendship.version = ACCOUNT_VERSION;
+
+        ctx.accounts.sender_profile.pending_request_count += 1;
+        ctx.accounts.friend_profile.pending_request_count += 1;
+
+        emit!(FriendRequestSent {
+            friendship: friendship.key(),
+            requester: sender,
+            recipient: friend,
+            created_at: friendship.created_at,
+        });
 
         msg!("Friend request sent from {} to {}", sender, friend);
         Ok(())
     }
 
-    pub fn accept_friend_request(ctx: Context<AcceptFriendRequest>) -> Result<()> {
+    /// 接受好友请求，并把双方写入各自的好友分页（页号由客户端选择有空位的页或下一新页）
+    pub fn accept_friend_request(
+        ctx: Context<AcceptFriendRequest>,
+        user_a_page: u32,
+        user_b_page: u32,
+    ) -> Result<()> {
         let friendship = &mut ctx.accounts.friendship;
 
         require!(
@@ -109,61 +333,286 @@ pub mod solamate_program {
 
         friendship.status = FriendshipStatus::Accepted;
 
-        let user_a_profile = &mut ctx.accounts.user_a_profile;
-        if user_a_profile.owner == Pubkey::default() {
-            user_a_profile.owner = friendship.user_a;
-            user_a_profile.pet_id = 0;
-            user_a_profile.friend_count = 0;
-            user_a_profile.created_at = Clock::get()?.unix_timestamp;
-            user_a_profile.bump = ctx.bumps.user_a_profile;
-        }
+        // 双方都必须已经创建 Profile，不再替对方自动创建
+        ctx.accounts.user_a_profile.friend_count += 1;
+        ctx.accounts.user_b_profile.friend_count += 1;
+        release_pending_request(
+            &mut ctx.accounts.user_a_profile,
+            &mut ctx.accounts.user_b_profile,
+        );
 
-        let user_b_profile = &mut ctx.accounts.user_b_profile;
-        if user_b_profile.owner == Pubkey::default() {
-            user_b_profile.owner = friendship.user_b;
-            user_b_profile.pet_id = 0;
-            user_b_profile.friend_count = 0;
-            user_b_profile.created_at = Clock::get()?.unix_timestamp;
-            user_b_profile.bump = ctx.bumps.user_b_profile;
-        }
+        index_friend(
+            &mut ctx.accounts.user_a_friend_index,
+            &mut ctx.accounts.user_a_friend_page,
+            friendship.user_a,
+            friendship.user_b,
+            user_a_page,
+            ctx.bumps.user_a_friend_index,
+            ctx.bumps.user_a_friend_page,
+        )?;
+        index_friend(
+            &mut ctx.accounts.user_b_friend_index,
+            &mut ctx.accounts.user_b_friend_page,
+            friendship.user_b,
+            friendship.user_a,
+            user_b_page,
+            ctx.bumps.user_b_friend_index,
+            ctx.bumps.user_b_friend_page,
+        )?;
+        friendship.indexed = true;
+        friendship.user_a_page = user_a_page;
+        friendship.user_b_page = user_b_page;
+
+        // 双方各自获得经验，受各自的每日上限约束
+        let now = Clock::get()?.unix_timestamp;
+        reward_pet_activity(
+            &mut ctx.accounts.pet_state,
+            &ctx.accounts.reward_config,
+            RewardAction::FriendAccepted,
+            now,
+        )?;
+        reward_pet_activity(
+            &mut ctx.accounts.requester_pet_state,
+            &ctx.accounts.reward_config,
+            RewardAction::FriendAccepted,
+            now,
+        )?;
+        // 请求方同样完成了一次加好友
+        track_quest_progress(&mut ctx.accounts.quest_progress, QuestKind::AddFriend, now);
+        track_quest_progress(
+            &mut ctx.accounts.requester_quest_progress,
+            QuestKind::AddFriend,
+            now,
+        );
 
-        user_a_profile.friend_count += 1;
-        user_b_profile.friend_count += 1;
+        emit!(FriendshipAccepted {
+            friendship: friendship.key(),
+            user_a: friendship.user_a,
+            user_b: friendship.user_b,
+            accepted_at: now,
+        });
 
         msg!("Friendship accepted");
         Ok(())
     }
 
+    /// 删除好友：只有双方可以调用，租金退还给当初付费的请求方
     pub fn remove_friend(ctx: Context<RemoveFriend>) -> Result<()> {
-        ctx.accounts.user_a_profile.friend_count =
-            ctx.accounts.user_a_profile.friend_count.saturating_sub(1);
-        ctx.accounts.user_b_profile.friend_count =
-            ctx.accounts.user_b_profile.friend_count.saturating_sub(1);
+        let friendship = &ctx.accounts.friendship;
+
+        // 待处理的请求从未计入好友数，只计入未处理请求数
+        if friendship.status == FriendshipStatus::Accepted {
+            ctx.accounts.user_a_profile.friend_count =
+                ctx.accounts.user_a_profile.friend_count.saturating_sub(1);
+            ctx.accounts.user_b_profile.friend_count =
+                ctx.accounts.user_b_profile.friend_count.saturating_sub(1);
+        } else {
+            release_pending_request(
+                &mut ctx.accounts.user_a_profile,
+                &mut ctx.accounts.user_b_profile,
+            );
+        }
+
+        unindex_friendship(
+            friendship,
+            [
+                (
+                    ctx.accounts.user_a_friend_index.as_mut(),
+                    ctx.accounts.user_a_friend_page.as_mut(),
+                ),
+                (
+                    ctx.accounts.user_b_friend_index.as_mut(),
+                    ctx.accounts.user_b_friend_page.as_mut(),
+                ),
+            ],
+        )?;
+
+        emit!(FriendRemoved {
+            friendship: friendship.key(),
+            user_a: friendship.user_a,
+            user_b: friendship.user_b,
+            removed_by: ctx.accounts.user.key(),
+        });
 
         msg!("Friendship removed");
         Ok(())
     }
 
+    /// 拒绝好友请求：由被请求方调用，关闭 PDA 并把租金退还给请求方
+    pub fn reject_friend_request(ctx: Context<RejectFriendRequest>) -> Result<()> {
+        let friendship = &ctx.accounts.friendship;
+        let user = ctx.accounts.user.key();
+
+        friendship.check_reject(&user)?;
+        release_pending_request(
+            &mut ctx.accounts.user_a_profile,
+            &mut ctx.accounts.user_b_profile,
+        );
+
+        emit!(FriendRequestRejected {
+            friendship: friendship.key(),
+            requester: friendship.requester,
+            recipient: user,
+        });
+
+        msg!("Friend request from {} rejected", friendship.requester);
+        Ok(())
+    }
+
+    /// 撤回好友请求：由请求方调用，关闭 PDA 并取回租金
+    pub fn cancel_friend_request(ctx: Context<CancelFriendRequest>) -> Result<()> {
+        let friendship = &ctx.accounts.friendship;
+
+        friendship.check_cancel(&ctx.accounts.user.key())?;
+        release_pending_request(
+            &mut ctx.accounts.user_a_profile,
+            &mut ctx.accounts.user_b_profile,
+        );
+
+        emit!(FriendRequestCancelled {
+            friendship: friendship.key(),
+            requester: friendship.requester,
+            recipient: friendship.recipient(),
+        });
+
+        msg!("Friend request to {} cancelled", friendship.recipient());
+        Ok(())
+    }
+
+    /// 拉黑用户：创建 block PDA，并删除双方之间已有的好友关系或请求
+    pub fn block_user(ctx: Context<BlockUser>) -> Result<()> {
+        let blocker = ctx.accounts.user.key();
+        let blocked = ctx.accounts.blocked.key();
+        require!(blocker != blocked, ErrorCode::CannotBlockSelf);
+
+        let now = Clock::get()?.unix_timestamp;
+        let user_block = &mut ctx.accounts.user_block;
+        user_block.blocker = blocker;
+        user_block.blocked = blocked;
+        user_block.created_at = now;
+        user_block.bump = ctx.bumps.user_block;
+        user_block.version = ACCOUNT_VERSION;
+
+        let friendship_info = ctx.accounts.friendship.to_account_info();
+        let friendship_removed = !friendship_info.data_is_empty();
+        if friendship_removed {
+            let friendship =
+                Friendship::try_deserialize(&mut &friendship_info.try_borrow_data()?[..])?;
+
+            let (Some(blocker_profile), Some(blocked_profile)) = (
+                ctx.accounts.blocker_profile.as_mut(),
+                ctx.accounts.blocked_profile.as_mut(),
+            ) else {
+                return err!(ErrorCode::BlockMissingFriendshipAccounts);
+            };
+            if friendship.status == FriendshipStatus::Accepted {
+                blocker_profile.friend_count = blocker_profile.friend_count.saturating_sub(1);
+                blocked_profile.friend_count = blocked_profile.friend_count.saturating_sub(1);
+            } else {
+                release_pending_request(blocker_profile, blocked_profile);
+            }
+
+            // 分页按 user_a / user_b 的顺序传入
+            let blocker_pages = (
+                ctx.accounts.blocker_friend_index.as_mut(),
+                ctx.accounts.blocker_friend_page.as_mut(),
+            );
+            let blocked_pages = (
+                ctx.accounts.blocked_friend_index.as_mut(),
+                ctx.accounts.blocked_friend_page.as_mut(),
+            );
+            let pages = if blocker == friendship.user_a {
+                [blocker_pages, blocked_pages]
+            } else {
+                [blocked_pages, blocker_pages]
+            };
+            unindex_friendship(&friendship, pages)?;
+
+            // 租金退还给当初付费的请求方
+            let requester = ctx
+                .accounts
+                .requester
+                .as_ref()
+                .filter(|requester| requester.key() == friendship.requester)
+                .ok_or(ErrorCode::BlockMissingFriendshipAccounts)?;
+            close_pda(&friendship_info, &requester.to_account_info())?;
+
+            emit!(FriendRemoved {
+                friendship: friendship_info.key(),
+                user_a: friendship.user_a,
+                user_b: friendship.user_b,
+                removed_by: blocker,
+            });
+        }
+
+        emit!(UserBlocked {
+            blocker,
+            blocked,
+            friendship_removed,
+            created_at: now,
+        });
+
+        msg!("User {} blocked {}", blocker, blocked);
+        Ok(())
+    }
+
+    /// 取消拉黑：关闭 block PDA，租金退还给拉黑方
+    pub fn unblock_user(ctx: Context<UnblockUser>) -> Result<()> {
+        let user_block = &ctx.accounts.user_block;
+
+        emit!(UserUnblocked {
+            blocker: user_block.blocker,
+            blocked: user_block.blocked,
+        });
+
+        msg!(
+            "User {} unblocked {}",
+            user_block.blocker,
+            user_block.blocked
+        );
+        Ok(())
+    }
+
     // ============================================================================
     // CHAT PROGRAM - 聊天系统
     // ============================================================================
 
+    /// 创建聊天室：user_a < user_b，付款人必须是其中一方；开启配置后还要求双方已是好友
     pub fn initialize_chat_room(ctx: Context<InitializeChatRoom>) -> Result<()> {
         let chat_room = &mut ctx.accounts.chat_room;
         let user_a = ctx.accounts.user_a.key();
         let user_b = ctx.accounts.user_b.key();
+        let payer = ctx.accounts.payer.key();
 
-        let (min_user, max_user) = if user_a < user_b {
-            (user_a, user_b)
-        } else {
-            (user_b, user_a)
-        };
+        require!(user_a < user_b, ErrorCode::InvalidKeyOrder);
+        require!(
+            payer == user_a || payer == user_b,
+            ErrorCode::NotChatRoomMember
+        );
+
+        let config = ChatConfig::load(&ctx.accounts.chat_config.to_account_info())?;
+        if matches!(config, Some(config) if config.require_friendship) {
+            require!(
+                matches!(
+                    ctx.accounts.friendship.as_deref(),
+                    Some(friendship) if friendship.status == FriendshipStatus::Accepted
+                ),
+                ErrorCode::FriendshipRequired
+            );
+        }
 
-        chat_room.user_a = min_user;
-        chat_room.user_b = max_user;
+        chat_room.user_a = user_a;
+        chat_room.user_b = user_b;
         chat_room.message_count = 0;
         chat_room.last_message_at = 0;
         chat_room.bump = ctx.bumps.chat_room;
+        chat_room.version = ACCOUNT_VERSION;
+
+        emit!(ChatRoomCreated {
+            chat_room: chat_room.key(),
+            user_a,
+            user_b,
+        });
 
         msg!("Chat room created");
         Ok(())
@@ -171,7 +620,69 @@ pub mod solamate_program {
 
     pub fn send_message(ctx: Context<SendMessage>, content: String) -> Result<()> {
         require!(
-            content.len() > 0 && content.len() <= 500,
+            !content.is_empty() && content.len() <= MESSAGE_MAX_LEN,
+            ErrorCode::InvalidMessageLength
+        );
+
+        let chat_room = &mut ctx.accounts.chat_room;
+        let message = &mut ctx.accounts.message;
+        let sender = ctx.accounts.sender.key();
+
+        require!(
+            sender == chat_room.user_a || sender == chat_room.user_b,
+            ErrorCode::NotChatRoomMember
+        );
+        require!(
+            ctx.accounts.sender_block.data_is_empty() && ctx.accounts.peer_block.data_is_empty(),
+            ErrorCode::UserBlocked
+        );
+
+        message.chat_room = chat_room.key();
+        message.sender = sender;
+        message.content = content.into_bytes();
+        message.encrypted = false;
+        message.message_index = chat_room.message_count;
+        message.timestamp = Clock::get()?.unix_timestamp;
+        message.bump = ctx.bumps.message;
+        message.version = ACCOUNT_VERSION;
+
+        chat_room.message_count += 1;
+        chat_room.last_message_at = message.timestamp;
+
+        reward_pet_activity(
+            &mut ctx.accounts.pet_state,
+            &ctx.accounts.reward_config,
+            RewardAction::MessageSent,
+            message.timestamp,
+        )?;
+        track_quest_progress(
+            &mut ctx.accounts.quest_progress,
+            QuestKind::SendMessages,
+            message.timestamp,
+        );
+
+        emit!(MessageSent {
+            chat_room: message.chat_room,
+            message: message.key(),
+            sender,
+            message_index: message.message_index,
+            timestamp: message.timestamp,
+            encrypted: false,
+        });
+
+        Ok(())
+    }
+
+    /// 发送端到端加密消息：密文由客户端用双方的 X25519 密钥加密，声明的密钥版本必须是双方当前版本
+    pub fn send_encrypted_message(
+        ctx: Context<SendEncryptedMessage>,
+        ciphertext: Vec<u8>,
+        nonce: [u8; ENCRYPTION_NONCE_LEN],
+        sender_key_version: u32,
+        recipient_key_version: u32,
+    ) -> Result<()> {
+        require!(
+            ciphertext.len() > ENCRYPTION_TAG_LEN && ciphertext.len() <= MESSAGE_CIPHERTEXT_MAX_LEN,
             ErrorCode::InvalidMessageLength
         );
 
@@ -183,17 +694,86 @@ pub mod solamate_program {
             sender == chat_room.user_a || sender == chat_room.user_b,
             ErrorCode::NotChatRoomMember
         );
+        require!(
+            ctx.accounts.sender_block.data_is_empty() && ctx.accounts.peer_block.data_is_empty(),
+            ErrorCode::UserBlocked
+        );
+        require!(
+            sender_key_version == ctx.accounts.sender_key.key_version
+                && recipient_key_version == ctx.accounts.recipient_key.key_version,
+            ErrorCode::EncryptionKeyVersionMismatch
+        );
 
         message.chat_room = chat_room.key();
         message.sender = sender;
-        message.content = content;
+        message.content = ciphertext;
+        message.encrypted = true;
+        message.nonce = nonce;
+        message.sender_key_version = sender_key_version;
+        message.recipient_key_version = recipient_key_version;
         message.message_index = chat_room.message_count;
         message.timestamp = Clock::get()?.unix_timestamp;
         message.bump = ctx.bumps.message;
+        message.version = ACCOUNT_VERSION;
 
         chat_room.message_count += 1;
         chat_room.last_message_at = message.timestamp;
 
+        reward_pet_activity(
+            &mut ctx.accounts.pet_state,
+            &ctx.accounts.reward_config,
+            RewardAction::MessageSent,
+            message.timestamp,
+        )?;
+        track_quest_progress(
+            &mut ctx.accounts.quest_progress,
+            QuestKind::SendMessages,
+            message.timestamp,
+        );
+
+        emit!(MessageSent {
+            chat_room: message.chat_room,
+            message: message.key(),
+            sender,
+            message_index: message.message_index,
+            timestamp: message.timestamp,
+            encrypted: true,
+        });
+
+        Ok(())
+    }
+
+    /// 发布或轮换 X25519 加密公钥，每次更新 key_version + 1
+    pub fn publish_encryption_key(
+        ctx: Context<PublishEncryptionKey>,
+        public_key: [u8; 32],
+    ) -> Result<()> {
+        require!(public_key != [0; 32], ErrorCode::InvalidEncryptionKey);
+
+        let key = &mut ctx.accounts.encryption_key;
+        require!(
+            key.public_key != public_key,
+            ErrorCode::InvalidEncryptionKey
+        );
+
+        key.owner = ctx.accounts.user.key();
+        key.public_key = public_key;
+        key.key_version += 1;
+        key.updated_at = Clock::get()?.unix_timestamp;
+        key.bump = ctx.bumps.encryption_key;
+        key.version = ACCOUNT_VERSION;
+
+        emit!(EncryptionKeyPublished {
+            owner: key.owner,
+            public_key,
+            key_version: key.key_version,
+        });
+
+        msg!(
+            "Encryption key v{} published for: {}",
+            key.key_version,
+            key.owner
+        );
         Ok(())
     }
 
@@ -202,98 +782,506 @@ pub mod solamate_program {
         let sender = ctx.accounts.sender.key();
 
         require!(message.sender == sender, ErrorCode::Unauthorized);
+        GroupChatRoom::check_live_member(&ctx.accounts.chat_room, &sender)?;
+
+        emit!(MessageDeleted {
+            chat_room: message.chat_room,
+            message: message.key(),
+            sender,
+            message_index: message.message_index,
+        });
 
         msg!("Message deleted");
         Ok(())
     }
 
-    // ============================================================================
-    // EXPENSE PROGRAM - 消费追踪
-    // ============================================================================
+    /// 编辑消息：仅发送者、仅明文，且在 ChatConfig 的编辑时限内；旧内容哈希写入事件
+    pub fn edit_message(ctx: Context<EditMessage>, new_content: String) -> Result<()> {
+        require!(
+            !new_content.is_empty() && new_content.len() <= MESSAGE_MAX_LEN,
+            ErrorCode::InvalidMessageLength
+        );
 
-    pub fn initialize_expense_stats(ctx: Context<InitializeExpenseStats>) -> Result<()> {
-        let stats = &mut ctx.accounts.expense_stats;
+        GroupChatRoom::check_live_member(&ctx.accounts.chat_room, &ctx.accounts.sender.key())?;
 
-        stats.owner = ctx.accounts.user.key();
-        stats.total_spent = 0;
-        stats.record_count = 0;
-        stats.dining_total = 0;
-        stats.shopping_total = 0;
-        stats.entertainment_total = 0;
-        stats.travel_total = 0;
-        stats.gifts_total = 0;
-        stats.bills_total = 0;
-        stats.other_total = 0;
-        stats.last_updated = Clock::get()?.unix_timestamp;
-        stats.bump = ctx.bumps.expense_stats;
+        let message = &mut ctx.accounts.message;
+        require!(!message.encrypted, ErrorCode::CannotEditEncryptedMessage);
 
-        msg!("Expense stats initialized");
+        let edit_window_secs = ChatConfig::load(&ctx.accounts.chat_config.to_account_info())?
+            .map_or(DEFAULT_MESSAGE_EDIT_WINDOW_SECS, |config| {
+                config.edit_window_secs
+            });
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now.saturating_sub(message.timestamp) <= edit_window_secs,
+            ErrorCode::MessageEditWindowExpired
+        );
+
+        let previous_content_hash =
+            anchor_lang::solana_program::hash::hash(&message.content).to_bytes();
+
+        message.content = new_content.into_bytes();
+        message.edited_at = now;
+        message.edit_count = message.edit_count.saturating_add(1);
+
+        emit!(MessageEdited {
+            chat_room: message.chat_room,
+            message: message.key(),
+            sender: message.sender,
+            message_index: message.message_index,
+            edit_count: message.edit_count,
+            previous_content_hash,
+            edited_at: now,
+        });
+
+        msg!("Message {} edited", message.message_index);
         Ok(())
     }
 
-    pub fn record_expense(
-        ctx: Context<RecordExpense>,
-        amount: u64,
-        category: ExpenseCategory,
-        description: String,
-        tx_signature: String,
+    /// 初始化聊天配置（仅限程序的升级权限持有者，之后成为管理员）
+    pub fn initialize_chat_config(
+        ctx: Context<InitializeChatConfig>,
+        require_friendship: bool,
+        edit_window_secs: i64,
     ) -> Result<()> {
-        require!(amount > 0, ErrorCode::InvalidAmount);
-        require!(description.len() <= 100, ErrorCode::DescriptionTooLong);
-        require!(tx_signature.len() > 0, ErrorCode::InvalidSignature);
-
-        let record = &mut ctx.accounts.expense_record;
-        let stats = &mut ctx.accounts.expense_stats;
-
-        record.owner = ctx.accounts.user.key();
-        record.recipient = ctx.accounts.recipient.key();
-        record.amount = amount;
-        record.category = category.clone();
-        record.description = description;
-        record.tx_signature = tx_signature;
-        record.record_index = stats.record_count;
-        record.timestamp = Clock::get()?.unix_timestamp;
-        record.bump = ctx.bumps.expense_record;
+        require!(edit_window_secs >= 0, ErrorCode::InvalidEditWindow);
 
-        stats.total_spent = stats.total_spent.checked_add(amount).unwrap();
-        stats.record_count += 1;
+        let config = &mut ctx.accounts.chat_config;
 
-        match category {
-            ExpenseCategory::Dining => {
-                stats.dining_total = stats.dining_total.checked_add(amount).unwrap()
-            }
-            ExpenseCategory::Shopping => {
-                stats.shopping_total = stats.shopping_total.checked_add(amount).unwrap()
-            }
-            ExpenseCategory::Entertainment => {
-                stats.entertainment_total = stats.entertainment_total.checked_add(amount).unwrap()
-            }
-            ExpenseCategory::Travel => {
-                stats.travel_total = stats.travel_total.checked_add(amount).unwrap()
-            }
-            ExpenseCategory::Gifts => {
-                stats.gifts_total = stats.gifts_total.checked_add(amount).unwrap()
-            }
-            ExpenseCategory::Bills => {
-                stats.bills_total = stats.bills_total.checked_add(amount).unwrap()
-            }
-            ExpenseCategory::Other => {
-                stats.other_total = stats.other_total.checked_add(amount).unwrap()
-            }
-        }
+        config.authority = ctx.accounts.authority.key();
+        config.require_friendship = require_friendship;
+        config.edit_window_secs = edit_window_secs;
+        config.bump = ctx.bumps.chat_config;
+        config.version = ACCOUNT_VERSION;
 
-        stats.last_updated = record.timestamp;
+        emit!(ChatConfigUpdated {
+            authority: config.authority,
+            require_friendship,
+            edit_window_secs,
+        });
 
-        msg!("Expense recorded");
+        msg!("Chat config initialized by: {}", config.authority);
         Ok(())
     }
 
-    pub fn delete_expense_record(ctx: Context<DeleteExpenseRecord>) -> Result<()> {
-        let record = &ctx.accounts.expense_record;
-        let stats = &mut ctx.accounts.expense_stats;
+    /// 更新聊天配置
+    pub fn update_chat_config(
+        ctx: Context<UpdateChatConfig>,
+        require_friendship: bool,
+        edit_window_secs: i64,
+    ) -> Result<()> {
+        require!(edit_window_secs >= 0, ErrorCode::InvalidEditWindow);
 
-        stats.total_spent = stats.total_spent.saturating_sub(record.amount);
-        stats.record_count = stats.record_count.saturating_sub(1);
+        let config = &mut ctx.accounts.chat_config;
+
+        config.require_friendship = require_friendship;
+        config.edit_window_secs = edit_window_secs;
+
+        emit!(ChatConfigUpdated {
+            authority: config.authority,
+            require_friendship,
+            edit_window_secs,
+        });
+
+        msg!("Chat config updated");
+        Ok(())
+    }
+
+    /// 发送分页消息：追加到当前页（page_index = page_count - 1），
+    /// 当前页放不下时客户端传 page_index = page_count 开启新页
+    pub fn send_paged_message(
+        ctx: Context<SendPagedMessage>,
+        content: String,
+        page_index: u32,
+    ) -> Result<()> {
+        require!(
+            !content.is_empty() && content.len() <= MESSAGE_MAX_LEN,
+            ErrorCode::InvalidMessageLength
+        );
+
+        let chat_room = &mut ctx.accounts.chat_room;
+        let page = &mut ctx.accounts.chat_page;
+        let sender = ctx.accounts.sender.key();
+
+        require!(
+            sender == chat_room.user_a || sender == chat_room.user_b,
+            ErrorCode::NotChatRoomMember
+        );
+        require!(
+            ctx.accounts.sender_block.data_is_empty() && ctx.accounts.peer_block.data_is_empty(),
+            ErrorCode::UserBlocked
+        );
+
+        if page_index == chat_room.page_count {
+            page.chat_room = chat_room.key();
+            page.page_index = page_index;
+            page.payer = sender;
+            page.entry_count = 0;
+            page.data = Vec::new();
+            page.bump = ctx.bumps.chat_page;
+            page.version = ACCOUNT_VERSION;
+
+            chat_room.page_count += 1;
+
+            emit!(ChatPageOpened {
+                chat_room: chat_room.key(),
+                page: page.key(),
+                page_index,
+                payer: sender,
+            });
+        }
+
+        let message_index = chat_room.message_count;
+        let timestamp = Clock::get()?.unix_timestamp;
+        page.append(
+            chat_room.member_slot(&sender),
+            message_index,
+            timestamp,
+            content.as_bytes(),
+        )?;
+
+        chat_room.message_count += 1;
+        chat_room.last_message_at = timestamp;
+
+        reward_pet_activity(
+            &mut ctx.accounts.pet_state,
+            &ctx.accounts.reward_config,
+            RewardAction::MessageSent,
+            timestamp,
+        )?;
+        track_quest_progress(
+            &mut ctx.accounts.quest_progress,
+            QuestKind::SendMessages,
+            timestamp,
+        );
+
+        emit!(PagedMessageSent {
+            chat_room: chat_room.key(),
+            page: page.key(),
+            page_index,
+            sender,
+            message_index,
+            timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// 设置聊天室的分页保留策略，0 表示保留全部；放宽保留任一成员即可，
+    /// 缩短保留（会让更早的页可被回收）需要双方共同签名
+    pub fn set_chat_retention(ctx: Context<SetChatRetention>, retained_pages: u16) -> Result<()> {
+        let chat_room = &mut ctx.accounts.chat_room;
+        let user = ctx.accounts.user.key();
+        let peer = ctx.accounts.peer.as_ref().map(|peer| peer.key());
+
+        chat_room.check_retention_change(&user, peer.as_ref(), retained_pages)?;
+
+        chat_room.retained_pages = retained_pages;
+
+        emit!(ChatRetentionUpdated {
+            chat_room: chat_room.key(),
+            updated_by: user,
+            retained_pages,
+        });
+
+        msg!("Chat retention set to {} pages", retained_pages);
+        Ok(())
+    }
+
+    /// 回收超出保留范围的最早一页，租金退还给该页的创建者；任何人都可以调用
+    pub fn reclaim_chat_page(ctx: Context<ReclaimChatPage>) -> Result<()> {
+        let chat_room = &mut ctx.accounts.chat_room;
+        let page = &ctx.accounts.chat_page;
+
+        require!(
+            chat_room.retained_pages > 0,
+            ErrorCode::ChatRetentionDisabled
+        );
+        require!(
+            page.page_index == chat_room.oldest_page,
+            ErrorCode::InvalidChatPage
+        );
+        require!(
+            page.page_index + (chat_room.retained_pages as u32) < chat_room.page_count,
+            ErrorCode::ChatPageRetained
+        );
+
+        chat_room.oldest_page += 1;
+
+        emit!(ChatPageReclaimed {
+            chat_room: chat_room.key(),
+            page: page.key(),
+            page_index: page.page_index,
+            payer: page.payer,
+        });
+
+        msg!("Chat page {} reclaimed", page.page_index);
+        Ok(())
+    }
+
+    /// 为聊天室启用压缩聊天记录：chat_tree 由客户端在同一交易中预先分配
+    /// （CHAT_TREE_ACCOUNT_LEN bytes，owner = SPL Account Compression），聊天室 PDA 作为树的 authority
+    pub fn initialize_chat_tree(ctx: Context<InitializeChatTree>) -> Result<()> {
+        let user = ctx.accounts.user.key();
+        let chat_room_info = ctx.accounts.chat_room.to_account_info();
+        let (user_a, user_b, bump, compressed) = {
+            let chat_room = &ctx.accounts.chat_room;
+            (
+                chat_room.user_a,
+                chat_room.user_b,
+                chat_room.bump,
+                chat_room.compressed,
+            )
+        };
+
+        require!(
+            user == user_a || user == user_b,
+            ErrorCode::NotChatRoomMember
+        );
+        require!(!compressed, ErrorCode::ChatTreeAlreadyInitialized);
+        require!(
+            ctx.accounts.chat_tree.data_len() == compression::CHAT_TREE_ACCOUNT_LEN,
+            ErrorCode::InvalidChatTree
+        );
+
+        let chat_room_seeds: &[&[u8]] = &[b"chat_room", user_a.as_ref(), user_b.as_ref(), &[bump]];
+        compression::init_empty_merkle_tree(
+            &ctx.accounts.compression_program.to_account_info(),
+            &ctx.accounts.chat_tree.to_account_info(),
+            &chat_room_info,
+            &ctx.accounts.noop_program.to_account_info(),
+            &[chat_room_seeds],
+        )?;
+
+        let tree_root = compression::tree_root(&ctx.accounts.chat_tree.try_borrow_data()?)?;
+
+        let chat_room = &mut ctx.accounts.chat_room;
+        chat_room.compressed = true;
+        chat_room.tree_root = tree_root;
+        chat_room.tree_leaf_count = 0;
+
+        emit!(ChatTreeInitialized {
+            chat_room: chat_room.key(),
+            chat_tree: ctx.accounts.chat_tree.key(),
+            max_depth: compression::CHAT_TREE_MAX_DEPTH as u32,
+            max_buffer_size: compression::CHAT_TREE_MAX_BUFFER_SIZE as u32,
+        });
+
+        msg!("Chat tree initialized: {}", ctx.accounts.chat_tree.key());
+        Ok(())
+    }
+
+    /// 发送压缩消息：内容通过 noop 程序写入交易日志供索引器读取，
+    /// 链上只把叶子追加到 Merkle tree，并在 ChatRoom 中记录最新 root 和叶子数
+    pub fn send_compressed_message(
+        ctx: Context<SendCompressedMessage>,
+        content: String,
+    ) -> Result<()> {
+        require!(
+            !content.is_empty() && content.len() <= MESSAGE_MAX_LEN,
+            ErrorCode::InvalidMessageLength
+        );
+
+        let sender = ctx.accounts.sender.key();
+        let chat_room_info = ctx.accounts.chat_room.to_account_info();
+        let chat_room_key = chat_room_info.key();
+        let (user_a, user_b, bump) = {
+            let chat_room = &ctx.accounts.chat_room;
+
+            require!(
+                sender == chat_room.user_a || sender == chat_room.user_b,
+                ErrorCode::NotChatRoomMember
+            );
+            require!(chat_room.compressed, ErrorCode::ChatTreeNotInitialized);
+            (chat_room.user_a, chat_room.user_b, chat_room.bump)
+        };
+        require!(
+            ctx.accounts.sender_block.data_is_empty() && ctx.accounts.peer_block.data_is_empty(),
+            ErrorCode::UserBlocked
+        );
+        require!(
+            compression::tree_authority(&ctx.accounts.chat_tree.try_borrow_data()?)
+                == Some(chat_room_key),
+            ErrorCode::InvalidChatTree
+        );
+
+        let record = compression::CompressedChatMessage {
+            chat_room: chat_room_key,
+            sender,
+            message_index: ctx.accounts.chat_room.message_count,
+            leaf_index: ctx.accounts.chat_room.tree_leaf_count,
+            timestamp: Clock::get()?.unix_timestamp,
+            content: content.into_bytes(),
+        };
+        let leaf = record.leaf_hash();
+
+        compression::log_to_noop(
+            &ctx.accounts.noop_program.to_account_info(),
+            record.try_to_vec()?,
+        )?;
+
+        let chat_room_seeds: &[&[u8]] = &[b"chat_room", user_a.as_ref(), user_b.as_ref(), &[bump]];
+        compression::append(
+            &ctx.accounts.compression_program.to_account_info(),
+            &ctx.accounts.chat_tree.to_account_info(),
+            &chat_room_info,
+            &ctx.accounts.noop_program.to_account_info(),
+            leaf,
+            &[chat_room_seeds],
+        )?;
+
+        let tree_root = compression::tree_root(&ctx.accounts.chat_tree.try_borrow_data()?)?;
+
+        let chat_room = &mut ctx.accounts.chat_room;
+        chat_room.tree_root = tree_root;
+        chat_room.tree_leaf_count += 1;
+        chat_room.message_count += 1;
+        chat_room.last_message_at = record.timestamp;
+
+        reward_pet_activity(
+            &mut ctx.accounts.pet_state,
+            &ctx.accounts.reward_config,
+            RewardAction::MessageSent,
+            record.timestamp,
+        )?;
+        track_quest_progress(
+            &mut ctx.accounts.quest_progress,
+            QuestKind::SendMessages,
+            record.timestamp,
+        );
+
+        emit!(CompressedMessageSent {
+            chat_room: chat_room_key,
+            sender,
+            message_index: record.message_index,
+            leaf_index: record.leaf_index,
+            leaf,
+            tree_root,
+            timestamp: record.timestamp,
+        });
+
+        Ok(())
+    }
+
+    // ============================================================================
+    // EXPENSE PROGRAM - 消费追踪
+    // ============================================================================
+
+    pub fn initialize_expense_stats(ctx: Context<InitializeExpenseStats>) -> Result<()> {
+        let stats = &mut ctx.accounts.expense_stats;
+
+        stats.owner = ctx.accounts.user.key();
+        stats.total_spent = 0;
+        stats.record_count = 0;
+        stats.dining_total = 0;
+        stats.shopping_total = 0;
+        stats.entertainment_total = 0;
+        stats.travel_total = 0;
+        stats.gifts_total = 0;
+        stats.bills_total = 0;
+        stats.other_total = 0;
+        stats.last_updated = Clock::get()?.unix_timestamp;
+        stats.bump = ctx.bumps.expense_stats;
+        stats.version = ACCOUNT_VERSION;
+
+        emit!(ExpenseStatsInitialized { owner: stats.owner });
+
+        msg!("Expense stats initialized");
+        Ok(())
+    }
+
+    pub fn record_expense(
+        ctx: Context<RecordExpense>,
+        amount: u64,
+        category: ExpenseCategory,
+        description: String,
+        tx_signature: String,
+    ) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(
+            description.len() <= DESCRIPTION_MAX_LEN,
+            ErrorCode::DescriptionTooLong
+        );
+        require!(
+            !tx_signature.is_empty() && tx_signature.len() <= TX_SIGNATURE_MAX_LEN,
+            ErrorCode::InvalidSignature
+        );
+
+        let record = &mut ctx.accounts.expense_record;
+        let stats = &mut ctx.accounts.expense_stats;
+
+        record.owner = ctx.accounts.user.key();
+        record.recipient = ctx.accounts.recipient.key();
+        record.amount = amount;
+        record.category = category.clone();
+        record.description = description;
+        record.tx_signature = tx_signature;
+        record.record_index = stats.record_count;
+        record.timestamp = Clock::get()?.unix_timestamp;
+        record.bump = ctx.bumps.expense_record;
+        record.version = ACCOUNT_VERSION;
+
+        stats.total_spent = stats.total_spent.checked_add(amount).unwrap();
+        stats.record_count += 1;
+
+        match category {
+            ExpenseCategory::Dining => {
+                stats.dining_total = stats.dining_total.checked_add(amount).unwrap()
+            }
+            ExpenseCategory::Shopping => {
+                stats.shopping_total = stats.shopping_total.checked_add(amount).unwrap()
+            }
+            ExpenseCategory::Entertainment => {
+                stats.entertainment_total = stats.entertainment_total.checked_add(amount).unwrap()
+            }
+            ExpenseCategory::Travel => {
+                stats.travel_total = stats.travel_total.checked_add(amount).unwrap()
+            }
+            ExpenseCategory::Gifts => {
+                stats.gifts_total = stats.gifts_total.checked_add(amount).unwrap()
+            }
+            ExpenseCategory::Bills => {
+                stats.bills_total = stats.bills_total.checked_add(amount).unwrap()
+            }
+            ExpenseCategory::Other => {
+                stats.other_total = stats.other_total.checked_add(amount).unwrap()
+            }
+        }
+
+        stats.last_updated = record.timestamp;
+
+        reward_pet_activity(
+            &mut ctx.accounts.pet_state,
+            &ctx.accounts.reward_config,
+            RewardAction::ExpenseRecorded,
+            record.timestamp,
+        )?;
+        track_quest_progress(
+            &mut ctx.accounts.quest_progress,
+            QuestKind::RecordExpenses,
+            record.timestamp,
+        );
+
+        emit!(ExpenseRecorded {
+            owner: record.owner,
+            recipient: record.recipient,
+            expense_record: record.key(),
+            amount,
+            category: record.category.clone(),
+            record_index: record.record_index,
+            timestamp: record.timestamp,
+        });
+
+        msg!("Expense recorded");
+        Ok(())
+    }
+
+    pub fn delete_expense_record(ctx: Context<DeleteExpenseRecord>) -> Result<()> {
+        let record = &ctx.accounts.expense_record;
+        let stats = &mut ctx.accounts.expense_stats;
+
+        stats.total_spent = stats.total_spent.saturating_sub(record.amount);
+        stats.record_count = stats.record_count.saturating_sub(1);
 
         match record.category {
             ExpenseCategory::Dining => {
@@ -321,10 +1309,28 @@ pub mod solamate_program {
 
         stats.last_updated = Clock::get()?.unix_timestamp;
 
+        emit!(ExpenseRecordDeleted {
+            owner: record.owner,
+            expense_record: record.key(),
+            amount: record.amount,
+            category: record.category.clone(),
+            record_index: record.record_index,
+        });
+
         msg!("Expense record deleted");
         Ok(())
     }
 
+    /// 关闭消费统计（需要先删除所有消费记录）
+    pub fn close_expense_stats(ctx: Context<CloseExpenseStats>) -> Result<()> {
+        emit!(ExpenseStatsClosed {
+            owner: ctx.accounts.user.key(),
+        });
+
+        msg!("Expense stats closed");
+        Ok(())
+    }
+
     // ============================================================================
     // FUNDING EVENTS - 福利社系统 (新功能)
     // ============================================================================
@@ -339,12 +1345,12 @@ pub mod solamate_program {
         timestamp: i64,
     ) -> Result<()> {
         require!(
-            title.len() > 0 && title.len() <= 64,
+            !title.is_empty() && title.len() <= TITLE_MAX_LEN,
             ErrorCode::InvalidTitle
         );
         require!(amount > 0, ErrorCode::InvalidAmount);
         require!(
-            ipfs_hash.len() > 0 && ipfs_hash.len() <= 64,
+            !ipfs_hash.is_empty() && ipfs_hash.len() <= IPFS_HASH_MAX_LEN,
             ErrorCode::InvalidIPFSHash
         );
 
@@ -362,6 +1368,7 @@ pub mod solamate_program {
         event.application_count = 0;
         event.approved_count = 0;
         event.bump = ctx.bumps.funding_event;
+        event.version = ACCOUNT_VERSION;
 
         // 转账 SOL 到活动账户
         let transfer_ix = anchor_lang::solana_program::system_instruction::transfer(
@@ -378,6 +1385,13 @@ pub mod solamate_program {
             ],
         )?;
 
+        emit!(FundingEventCreated {
+            funding_event: event.key(),
+            creator,
+            total_amount: amount,
+            deadline,
+        });
+
         msg!("Funding event created: {}", event.title);
         Ok(())
     }
@@ -390,7 +1404,7 @@ pub mod solamate_program {
     ) -> Result<()> {
         require!(requested_amount > 0, ErrorCode::InvalidAmount);
         require!(
-            ipfs_hash.len() > 0 && ipfs_hash.len() <= 64,
+            !ipfs_hash.is_empty() && ipfs_hash.len() <= IPFS_HASH_MAX_LEN,
             ErrorCode::InvalidIPFSHash
         );
 
@@ -414,9 +1428,17 @@ pub mod solamate_program {
         application.status = ApplicationStatus::Pending;
         application.applied_at = Clock::get()?.unix_timestamp;
         application.bump = ctx.bumps.application;
+        application.version = ACCOUNT_VERSION;
 
         event.application_count += 1;
 
+        emit!(FundingApplied {
+            funding_event: event.key(),
+            application: application.key(),
+            applicant: application.applicant,
+            requested_amount,
+        });
+
         msg!("Application submitted");
         Ok(())
     }
@@ -446,6 +1468,13 @@ pub mod solamate_program {
         application.status = ApplicationStatus::Approved;
         event.approved_count += 1;
 
+        emit!(ApplicationApproved {
+            funding_event: event.key(),
+            application: application.key(),
+            applicant: application.applicant,
+            approved_amount,
+        });
+
         msg!("Application approved: {} lamports", approved_amount);
         Ok(())
     }
@@ -466,6 +1495,12 @@ pub mod solamate_program {
 
         application.status = ApplicationStatus::Rejected;
 
+        emit!(ApplicationRejected {
+            funding_event: event.key(),
+            application: application.key(),
+            applicant: application.applicant,
+        });
+
         msg!("Application rejected");
         Ok(())
     }
@@ -497,6 +1532,14 @@ pub mod solamate_program {
         event.remaining_amount -= amount;
         application.status = ApplicationStatus::Paid;
 
+        emit!(FundsDisbursed {
+            funding_event: event.key(),
+            application: application.key(),
+            applicant: application.applicant,
+            amount,
+            remaining_amount: event.remaining_amount,
+        });
+
         msg!("Funds disbursed: {} lamports", amount);
         Ok(())
     }
@@ -524,6 +1567,12 @@ pub mod solamate_program {
         event.status = EventStatus::Closed;
         event.remaining_amount = 0;
 
+        emit!(FundingEventClosed {
+            funding_event: event.key(),
+            creator: event.creator,
+            refunded_amount: remaining,
+        });
+
         msg!("Event closed");
         Ok(())
     }
@@ -542,7 +1591,7 @@ pub mod solamate_program {
         timestamp: i64,
     ) -> Result<()> {
         require!(
-            title.len() > 0 && title.len() <= 64,
+            !title.is_empty() && title.len() <= TITLE_MAX_LEN,
             ErrorCode::InvalidTitle
         );
         require!(total_amount > 0, ErrorCode::InvalidAmount);
@@ -551,7 +1600,7 @@ pub mod solamate_program {
             ErrorCode::InvalidMemberCount
         );
         require!(
-            ipfs_hash.len() > 0 && ipfs_hash.len() <= 64,
+            !ipfs_hash.is_empty() && ipfs_hash.len() <= IPFS_HASH_MAX_LEN,
             ErrorCode::InvalidIPFSHash
         );
 
@@ -568,6 +1617,15 @@ pub mod solamate_program {
         split.settled_count = 0;
         split.status = SplitStatus::Active;
         split.bump = ctx.bumps.group_split;
+        split.version = ACCOUNT_VERSION;
+
+        emit!(GroupSplitCreated {
+            group_split: split.key(),
+            creator,
+            total_amount,
+            member_count,
+            amount_per_person: split.amount_per_person,
+        });
 
         msg!("Group split created: {}", split.title);
         Ok(())
@@ -593,6 +1651,13 @@ pub mod solamate_program {
         member.paid = false;
         member.paid_at = 0;
         member.bump = ctx.bumps.split_member;
+        member.version = ACCOUNT_VERSION;
+
+        emit!(SplitMemberAdded {
+            group_split: split.key(),
+            member: member_pubkey,
+            amount_owed: member.amount_owed,
+        });
 
         msg!("Member added to split: {}", member_pubkey);
         Ok(())
@@ -622,6 +1687,25 @@ pub mod solamate_program {
             split.status = SplitStatus::Settled;
         }
 
+        // 只有成员自己付款才奖励经验，避免创建者代标记来刷经验
+        if ctx.accounts.payer.key() == member.member {
+            reward_pet_activity(
+                &mut ctx.accounts.pet_state,
+                &ctx.accounts.reward_config,
+                RewardAction::SplitPaid,
+                member.paid_at,
+            )?;
+        }
+
+        emit!(SplitPaid {
+            group_split: split.key(),
+            member: member.member,
+            marked_by: ctx.accounts.payer.key(),
+            amount: member.amount_owed,
+            paid_at: member.paid_at,
+            settled: split.status == SplitStatus::Settled,
+        });
+
         msg!("Member marked as paid: {}", member.member);
         Ok(())
     }
@@ -637,782 +1721,7023 @@ pub mod solamate_program {
 
         split.status = SplitStatus::Closed;
 
+        emit!(GroupSplitClosed {
+            group_split: split.key(),
+            creator: split.creator,
+        });
+
         msg!("Group split closed");
         Ok(())
     }
-}
-
-// ============================================================================
-// ACCOUNT STRUCTURES - 账户结构
-// ============================================================================
 
-#[account]
-pub struct UserProfile {
-    pub owner: Pubkey,     // 32 bytes
-    pub username: String,  // 4 + 20 = 24 bytes (max 20 chars)
-    pub avatar: String,    // 4 + 10 = 14 bytes (e.g., "1.png")
-    pub pet_id: u8,        // 1 byte
-    pub friend_count: u32, // 4 bytes
-    pub created_at: i64,   // 8 bytes
-    pub bump: u8,          // 1 byte
-}
+    // ============================================================================
+    // PET SYSTEM - 宠物系统 (新功能)
+    // ============================================================================
 
-impl UserProfile {
-    // 8 (discriminator) + 32 (owner) + 24 (username) + 14 (avatar) + 1 (pet_id) + 4 (friend_count) + 8 (created_at) + 1 (bump)
-    pub const LEN: usize = 8 + 32 + 24 + 14 + 1 + 4 + 8 + 1;
-}
+    /// 孵化宠物：根据 Profile 中已选择的 pet_id 创建链上宠物状态
+    pub fn initialize_pet(ctx: Context<InitializePet>) -> Result<()> {
+        let profile = &ctx.accounts.user_profile;
+        require!(
+            profile.pet_id >= 1 && profile.pet_id <= 10,
+            ErrorCode::PetNotSelected
+        );
 
-#[account]
-pub struct Friendship {
-    pub user_a: Pubkey,
-    pub user_b: Pubkey,
-    pub requester: Pubkey,
-    pub status: FriendshipStatus,
-    pub created_at: i64,
-    pub bump: u8,
-}
+        let now = Clock::get()?.unix_timestamp;
+        let pet = &mut ctx.accounts.pet_state;
+
+        pet.owner = ctx.accounts.user.key();
+        pet.pet_id = profile.pet_id;
+        pet.level = 1;
+        pet.xp = 0;
+        pet.happiness = PET_STAT_MAX;
+        pet.energy = PET_STAT_MAX;
+        pet.last_fed = now;
+        pet.last_played = 0;
+        pet.last_decay_at = now;
+        pet.total_interactions = 0;
+        pet.created_at = now;
+        pet.stage = PetStage::Baby;
+        pet.evolved_at = 0;
+        pet.nft_mint = Pubkey::default();
+        pet.bump = ctx.bumps.pet_state;
+        pet.version = ACCOUNT_VERSION;
+
+        emit!(PetHatched {
+            owner: pet.owner,
+            pet_id: pet.pet_id,
+            created_at: now,
+        });
 
-impl Friendship {
-    pub const LEN: usize = 8 + 32 + 32 + 32 + 1 + 8 + 1;
-}
+        msg!(
+            "Pet #{} ({}) hatched for: {}",
+            pet.pet_id,
+            pet_species(pet.pet_id).name,
+            pet.owner
+        );
+        Ok(())
+    }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
-pub enum FriendshipStatus {
-    Pending,
-    Accepted,
-}
+    /// 喂食宠物（每 6 小时一次）
+    pub fn feed_pet(ctx: Context<FeedPet>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let pet = &mut ctx.accounts.pet_state;
 
-#[account]
-pub struct ChatRoom {
-    pub user_a: Pubkey,
-    pub user_b: Pubkey,
-    pub message_count: u64,
-    pub last_message_at: i64,
-    pub bump: u8,
-}
+        require!(
+            now >= pet.last_fed.saturating_add(PET_FEED_COOLDOWN),
+            ErrorCode::PetFeedCooldown
+        );
+
+        pet.apply_decay(now);
+        pet.happiness = pet
+            .happiness
+            .saturating_add(PET_FEED_HAPPINESS)
+            .min(PET_STAT_MAX);
+        pet.energy = pet.energy.saturating_add(PET_FEED_ENERGY).min(PET_STAT_MAX);
+        pet.last_fed = now;
+        pet.total_interactions += 1;
+        let earned = apply_bps(PET_FEED_XP, pet.multipliers().care_bps);
+        let leveled_up = pet.add_xp(earned);
+
+        emit!(PetCared {
+            owner: pet.owner,
+            action: PetCareAction::Feed,
+            xp_earned: earned,
+            level: pet.level,
+            leveled_up,
+            happiness: pet.happiness,
+            energy: pet.energy,
+        });
+
+        msg!("Pet fed: level {} with {} XP", pet.level, pet.xp);
+        Ok(())
+    }
+
+    /// 与宠物玩耍（每 4 小时一次，消耗能量）
+    pub fn play_with_pet(ctx: Context<PlayWithPet>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let pet = &mut ctx.accounts.pet_state;
+
+        require!(
+            now >= pet.last_played.saturating_add(PET_PLAY_COOLDOWN),
+            ErrorCode::PetPlayCooldown
+        );
+
+        pet.apply_decay(now);
+        require!(pet.energy >= PET_PLAY_ENERGY_COST, ErrorCode::PetTooTired);
+
+        pet.energy -= PET_PLAY_ENERGY_COST;
+        pet.happiness = pet
+            .happiness
+            .saturating_add(PET_PLAY_HAPPINESS)
+            .min(PET_STAT_MAX);
+        pet.last_played = now;
+        pet.total_interactions += 1;
+        let earned = apply_bps(PET_PLAY_XP, pet.multipliers().care_bps);
+        let leveled_up = pet.add_xp(earned);
+
+        emit!(PetCared {
+            owner: pet.owner,
+            action: PetCareAction::Play,
+            xp_earned: earned,
+            level: pet.level,
+            leveled_up,
+            happiness: pet.happiness,
+            energy: pet.energy,
+        });
+
+        msg!("Played with pet: level {} with {} XP", pet.level, pet.xp);
+        Ok(())
+    }
+
+    /// 初始化经验奖励表（仅限程序的升级权限持有者，之后成为管理员）
+    pub fn initialize_reward_config(
+        ctx: Context<InitializeRewardConfig>,
+        xp_rewards: [u32; REWARD_ACTION_COUNT],
+        daily_caps: [u32; REWARD_ACTION_COUNT],
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.reward_config;
+
+        config.authority = ctx.accounts.authority.key();
+        config.xp_rewards = xp_rewards;
+        config.daily_caps = daily_caps;
+        config.bump = ctx.bumps.reward_config;
+        config.version = ACCOUNT_VERSION;
+
+        emit!(RewardConfigUpdated {
+            authority: config.authority,
+            xp_rewards,
+            daily_caps,
+        });
+
+        msg!("Reward config initialized by: {}", config.authority);
+        Ok(())
+    }
+
+    /// 更新经验奖励表
+    pub fn update_reward_config(
+        ctx: Context<UpdateRewardConfig>,
+        xp_rewards: [u32; REWARD_ACTION_COUNT],
+        daily_caps: [u32; REWARD_ACTION_COUNT],
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.reward_config;
+
+        config.xp_rewards = xp_rewards;
+        config.daily_caps = daily_caps;
+
+        emit!(RewardConfigUpdated {
+            authority: config.authority,
+            xp_rewards,
+            daily_caps,
+        });
+
+        msg!("Reward config updated");
+        Ok(())
+    }
+
+    /// 宠物进化：达到等级和成就门槛后进入下一形态，不可逆
+    pub fn evolve_pet(ctx: Context<EvolvePet>) -> Result<()> {
+        let pet = &mut ctx.accounts.pet_state;
+        let next_stage = pet.stage.next().ok_or(ErrorCode::PetFullyEvolved)?;
+        let requirement = next_stage.requirement();
+
+        let expense_records = ctx
+            .accounts
+            .expense_stats
+            .as_ref()
+            .map_or(0, |stats| stats.record_count);
+
+        requirement.check(
+            pet.level,
+            ctx.accounts.user_profile.friend_count,
+            expense_records,
+        )?;
+
+        pet.stage = next_stage;
+        pet.evolved_at = Clock::get()?.unix_timestamp;
+
+        emit!(PetEvolved {
+            owner: pet.owner,
+            pet_id: pet.pet_id,
+            stage: pet.stage,
+            level: pet.level,
+            evolved_at: pet.evolved_at,
+        });
+
+        msg!("Pet evolved to {:?}", pet.stage);
+        Ok(())
+    }
+
+    /// 将宠物铸造为 NFT（Metaplex 元数据 + Master Edition，供应量 1）
+    pub fn mint_pet_nft(ctx: Context<MintPetNft>, uri: String) -> Result<()> {
+        require!(
+            ctx.accounts.pet_state.nft_mint == Pubkey::default(),
+            ErrorCode::PetAlreadyMinted
+        );
+        require!(
+            uri.len() <= PET_NFT_MAX_URI_LEN,
+            ErrorCode::InvalidMetadataUri
+        );
+
+        let authority_seeds: &[&[u8]] = &[b"pet_nft_authority", &[ctx.bumps.nft_authority]];
+        let signer = &[authority_seeds];
+
+        mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                MintTo {
+                    mint: ctx.accounts.pet_mint.to_account_info(),
+                    to: ctx.accounts.owner_token_account.to_account_info(),
+                    authority: ctx.accounts.nft_authority.to_account_info(),
+                },
+                signer,
+            ),
+            1,
+        )?;
+
+        create_metadata_accounts_v3(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_metadata_program.to_account_info(),
+                CreateMetadataAccountsV3 {
+                    metadata: ctx.accounts.metadata.to_account_info(),
+                    mint: ctx.accounts.pet_mint.to_account_info(),
+                    mint_authority: ctx.accounts.nft_authority.to_account_info(),
+                    payer: ctx.accounts.user.to_account_info(),
+                    update_authority: ctx.accounts.nft_authority.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                    rent: ctx.accounts.rent.to_account_info(),
+                },
+                signer,
+            ),
+            pet_nft_data(&ctx.accounts.pet_state, uri),
+            true,
+            true,
+            None,
+        )?;
+
+        // Master Edition 接管 mint authority，max_supply = 0 保证只有这一枚
+        create_master_edition_v3(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_metadata_program.to_account_info(),
+                CreateMasterEditionV3 {
+                    edition: ctx.accounts.master_edition.to_account_info(),
+                    mint: ctx.accounts.pet_mint.to_account_info(),
+                    update_authority: ctx.accounts.nft_authority.to_account_info(),
+                    mint_authority: ctx.accounts.nft_authority.to_account_info(),
+                    payer: ctx.accounts.user.to_account_info(),
+                    metadata: ctx.accounts.metadata.to_account_info(),
+                    token_program: ctx.accounts.token_program.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                    rent: ctx.accounts.rent.to_account_info(),
+                },
+                signer,
+            ),
+            Some(0),
+        )?;
+
+        let pet = &mut ctx.accounts.pet_state;
+        pet.nft_mint = ctx.accounts.pet_mint.key();
+
+        emit!(PetNftMinted {
+            owner: pet.owner,
+            pet_id: pet.pet_id,
+            mint: pet.nft_mint,
+        });
+
+        msg!("Pet minted as NFT: {}", pet.nft_mint);
+        Ok(())
+    }
+
+    /// 用最新的宠物状态（等级、形态）刷新 NFT 元数据
+    pub fn refresh_pet_nft(ctx: Context<RefreshPetNft>, uri: String) -> Result<()> {
+        require!(
+            uri.len() <= PET_NFT_MAX_URI_LEN,
+            ErrorCode::InvalidMetadataUri
+        );
+
+        let authority_seeds: &[&[u8]] = &[b"pet_nft_authority", &[ctx.bumps.nft_authority]];
+
+        update_metadata_accounts_v2(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_metadata_program.to_account_info(),
+                UpdateMetadataAccountsV2 {
+                    metadata: ctx.accounts.metadata.to_account_info(),
+                    update_authority: ctx.accounts.nft_authority.to_account_info(),
+                },
+                &[authority_seeds],
+            ),
+            None,
+            Some(pet_nft_data(&ctx.accounts.pet_state, uri)),
+            None,
+            None,
+        )?;
+
+        emit!(PetNftRefreshed {
+            owner: ctx.accounts.pet_state.owner,
+            mint: ctx.accounts.pet_state.nft_mint,
+        });
+
+        msg!("Pet NFT metadata refreshed");
+        Ok(())
+    }
+
+    /// NFT 转手后，由新持有人把宠物状态迁移到自己名下。
+    /// 每个钱包只有一个宠物 PDA：新持有人已有宠物时返回 NewOwnerHasPet，需要先用 release_pet
+    /// 释放未铸造的宠物，或把自己已铸造的宠物 NFT 转出（由对方同步）/ 销毁（release_burned_pet）
+    pub fn sync_pet_nft_owner(ctx: Context<SyncPetNftOwner>) -> Result<()> {
+        let new_owner = ctx.accounts.new_owner.key();
+        require!(
+            ctx.accounts.new_pet_state.owner == Pubkey::default(),
+            ErrorCode::NewOwnerHasPet
+        );
+
+        let mut pet = (*ctx.accounts.pet_state).clone();
+        pet.owner = new_owner;
+        pet.bump = ctx.bumps.new_pet_state;
+
+        ctx.accounts.previous_profile.pet_id = 0;
+        ctx.accounts.new_profile.pet_id = pet.pet_id;
+
+        emit!(PetNftOwnerSynced {
+            mint: pet.nft_mint,
+            previous_owner: ctx.accounts.previous_owner.key(),
+            new_owner,
+        });
+
+        ctx.accounts.new_pet_state.set_inner(pet);
+
+        msg!(
+            "Pet NFT ownership moved from {} to {}",
+            ctx.accounts.previous_owner.key(),
+            new_owner
+        );
+        Ok(())
+    }
+
+    /// 释放未铸造成 NFT 的宠物，租金退还给主人，之后可以重新选择宠物或接收别人的宠物 NFT
+    pub fn release_pet(ctx: Context<ReleasePet>) -> Result<()> {
+        ctx.accounts.owner_profile.pet_id = 0;
+
+        emit!(PetReleased {
+            owner: ctx.accounts.owner.key(),
+            pet_id: ctx.accounts.pet_state.pet_id,
+        });
+
+        msg!("Pet released by: {}", ctx.accounts.owner.key());
+        Ok(())
+    }
+
+    /// NFT 被销毁后释放宠物，租金退还给原主人
+    pub fn release_burned_pet(ctx: Context<ReleaseBurnedPet>) -> Result<()> {
+        ctx.accounts.owner_profile.pet_id = 0;
+
+        emit!(BurnedPetReleased {
+            owner: ctx.accounts.pet_state.owner,
+            mint: ctx.accounts.pet_mint.key(),
+        });
+
+        msg!("Burned pet NFT released: {}", ctx.accounts.pet_mint.key());
+        Ok(())
+    }
+
+    /// 查询宠物种类的经验加成（通过 return data 返回）
+    pub fn get_pet_multipliers(ctx: Context<GetPetMultipliers>) -> Result<PetMultipliers> {
+        Ok(ctx.accounts.pet_state.multipliers())
+    }
+
+    // ============================================================================
+    // DAILY QUESTS - 每日任务 (新功能)
+    // ============================================================================
+
+    /// 初始化每日任务进度
+    pub fn initialize_quest_progress(ctx: Context<InitializeQuestProgress>) -> Result<()> {
+        let quests = &mut ctx.accounts.quest_progress;
+
+        quests.owner = ctx.accounts.user.key();
+        quests.day = Clock::get()?.unix_timestamp.div_euclid(SECONDS_PER_DAY);
+        quests.progress = [0; QUEST_COUNT];
+        quests.claimed = [false; QUEST_COUNT];
+        quests.streak = 0;
+        quests.best_streak = 0;
+        quests.last_completed_day = 0;
+        quests.bump = ctx.bumps.quest_progress;
+        quests.version = ACCOUNT_VERSION;
+
+        emit!(QuestProgressInitialized {
+            owner: quests.owner,
+        });
+
+        msg!("Quest progress initialized for: {}", quests.owner);
+        Ok(())
+    }
+
+    /// 领取每日任务奖励（每个任务每个 UTC 日一次）
+    pub fn claim_quest_reward(ctx: Context<ClaimQuestReward>, quest: QuestKind) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let quests = &mut ctx.accounts.quest_progress;
+        let pet = &mut ctx.accounts.pet_state;
+        let definition = quest.definition();
+        let slot = quest as usize;
+
+        quests.roll_over(now);
+        require!(
+            quests.progress[slot] >= definition.target,
+            ErrorCode::QuestNotCompleted
+        );
+        require!(!quests.claimed[slot], ErrorCode::QuestAlreadyClaimed);
+
+        quests.claimed[slot] = true;
+        let earned = apply_bps(definition.reward_xp, pet.multipliers().quest_bps);
+        pet.add_xp(earned);
+
+        // 当天所有任务都领取后计入连续天数
+        if quests.claimed.iter().all(|&claimed| claimed) {
+            quests.streak = if quests.last_completed_day == quests.day - 1 {
+                quests.streak + 1
+            } else {
+                1
+            };
+            quests.best_streak = quests.best_streak.max(quests.streak);
+            quests.last_completed_day = quests.day;
+        }
+
+        emit!(QuestRewardClaimed {
+            owner: quests.owner,
+            quest,
+            xp_earned: earned,
+            streak: quests.streak,
+            day: quests.day,
+        });
+
+        msg!(
+            "Quest {:?} claimed for {} XP, streak {}",
+            quest,
+            earned,
+            quests.streak
+        );
+        Ok(())
+    }
+
+    // ============================================================================
+    // CIRCLES - 好友圈 (新功能)
+    // ============================================================================
+
+    /// 创建好友圈，创建者成为 Owner
+    pub fn create_circle(ctx: Context<CreateCircle>, name: String, timestamp: i64) -> Result<()> {
+        require!(
+            !name.is_empty() && name.len() <= CIRCLE_NAME_MAX_LEN,
+            ErrorCode::InvalidCircleName
+        );
+
+        let circle = &mut ctx.accounts.circle;
+        let owner = ctx.accounts.owner.key();
+
+        circle.owner = owner;
+        circle.name = name;
+        circle.members = vec![MemberEntry {
+            member: owner,
+            role: MemberRole::Owner,
+        }];
+        circle.invites = Vec::new();
+        circle.created_at = timestamp;
+        circle.bump = ctx.bumps.circle;
+        circle.version = ACCOUNT_VERSION;
+
+        emit!(CircleCreated {
+            circle: circle.key(),
+            owner,
+            name: circle.name.clone(),
+            created_at: timestamp,
+        });
+
+        msg!("Circle created: {}", circle.name);
+        Ok(())
+    }
+
+    /// 邀请用户加入好友圈（Owner 或 Admin）
+    pub fn invite_to_circle(ctx: Context<InviteToCircle>, invitee: Pubkey) -> Result<()> {
+        let circle = &mut ctx.accounts.circle;
+        let user = ctx.accounts.user.key();
+
+        circle.check_invite(&user, &invitee)?;
+
+        circle.invites.push(invitee);
+
+        emit!(CircleInvited {
+            circle: circle.key(),
+            invited_by: user,
+            invitee,
+        });
+
+        msg!("Invited {} to circle {}", invitee, circle.name);
+        Ok(())
+    }
+
+    /// 接受邀请加入好友圈
+    pub fn join_circle(ctx: Context<JoinCircle>) -> Result<()> {
+        let circle = &mut ctx.accounts.circle;
+        let user = ctx.accounts.user.key();
+
+        let position = circle.check_join(&user)?;
+
+        circle.invites.swap_remove(position);
+        circle.members.push(MemberEntry {
+            member: user,
+            role: MemberRole::Member,
+        });
+
+        emit!(CircleJoined {
+            circle: circle.key(),
+            member: user,
+        });
+
+        msg!("{} joined circle {}", user, circle.name);
+        Ok(())
+    }
+
+    /// 退出好友圈（Owner 只能解散）
+    pub fn leave_circle(ctx: Context<LeaveCircle>) -> Result<()> {
+        let circle = &mut ctx.accounts.circle;
+        let user = ctx.accounts.user.key();
+
+        circle.check_leave(&user)?;
+
+        circle.members.retain(|member| member.member != user);
+
+        emit!(CircleLeft {
+            circle: circle.key(),
+            member: user,
+        });
+
+        msg!("{} left circle {}", user, circle.name);
+        Ok(())
+    }
+
+    /// 移除成员：Owner 可以移除任何人，Admin 只能移除普通成员
+    pub fn remove_circle_member(ctx: Context<RemoveCircleMember>, member: Pubkey) -> Result<()> {
+        let circle = &mut ctx.accounts.circle;
+        let user = ctx.accounts.user.key();
+
+        circle.check_remove(&user, &member)?;
+
+        circle.members.retain(|entry| entry.member != member);
+
+        emit!(CircleMemberRemoved {
+            circle: circle.key(),
+            member,
+            removed_by: user,
+        });
+
+        msg!("{} removed from circle {}", member, circle.name);
+        Ok(())
+    }
+
+    /// 设置成员角色（仅 Owner，只能在 Admin 和 Member 之间切换）
+    pub fn set_circle_role(
+        ctx: Context<SetCircleRole>,
+        member: Pubkey,
+        role: MemberRole,
+    ) -> Result<()> {
+        let circle = &mut ctx.accounts.circle;
+
+        require!(role != MemberRole::Owner, ErrorCode::InvalidCircleRole);
+        let entry = circle
+            .members
+            .iter_mut()
+            .find(|entry| entry.member == member)
+            .ok_or(ErrorCode::NotCircleMember)?;
+        require!(
+            entry.role != MemberRole::Owner,
+            ErrorCode::InvalidCircleRole
+        );
+
+        entry.role = role;
+
+        emit!(CircleRoleChanged {
+            circle: circle.key(),
+            member,
+            role,
+        });
+
+        msg!("{} is now {:?} in circle {}", member, role, circle.name);
+        Ok(())
+    }
+
+    /// 解散好友圈，租金退还给 Owner
+    pub fn dissolve_circle(ctx: Context<DissolveCircle>) -> Result<()> {
+        let circle = &ctx.accounts.circle;
+
+        emit!(CircleDissolved {
+            circle: circle.key(),
+            owner: circle.owner,
+        });
+
+        msg!("Circle dissolved: {}", circle.name);
+        Ok(())
+    }
+
+    // ============================================================================
+    // GROUP CHAT - 群聊 (新功能)
+    // ============================================================================
+
+    /// 创建群聊，创建者成为 Owner；topic 可关联分账、福利活动或好友圈
+    pub fn create_group_chat(
+        ctx: Context<CreateGroupChat>,
+        name: String,
+        topic: Pubkey,
+        timestamp: i64,
+    ) -> Result<()> {
+        validation::validate_group_chat_name(&name)?;
+        validation::validate_group_chat_topic(
+            &topic,
+            ctx.accounts
+                .topic_account
+                .as_ref()
+                .map(|account| account.as_ref()),
+        )?;
+
+        let room = &mut ctx.accounts.group_chat;
+        let owner = ctx.accounts.owner.key();
+
+        room.owner = owner;
+        room.name = name;
+        room.topic = topic;
+        room.members = vec![MemberEntry {
+            member: owner,
+            role: MemberRole::Owner,
+        }];
+        room.invites = Vec::new();
+        room.message_count = 0;
+        room.last_message_at = 0;
+        room.created_at = timestamp;
+        room.bump = ctx.bumps.group_chat;
+        room.version = ACCOUNT_VERSION;
+
+        emit!(GroupChatCreated {
+            group_chat: room.key(),
+            owner,
+            name: room.name.clone(),
+            topic,
+        });
+
+        msg!("Group chat created: {}", room.name);
+        Ok(())
+    }
+
+    /// 邀请用户加入群聊（Owner 或 Admin）
+    pub fn invite_to_group_chat(ctx: Context<InviteToGroupChat>, invitee: Pubkey) -> Result<()> {
+        let room = &mut ctx.accounts.group_chat;
+        let user = ctx.accounts.user.key();
+
+        room.check_invite(&user, &invitee)?;
+
+        room.invites.push(invitee);
+
+        emit!(GroupChatInvited {
+            group_chat: room.key(),
+            invited_by: user,
+            invitee,
+        });
+
+        msg!("Invited {} to group chat {}", invitee, room.name);
+        Ok(())
+    }
+
+    /// 接受邀请加入群聊
+    pub fn join_group_chat(ctx: Context<JoinGroupChat>) -> Result<()> {
+        let room = &mut ctx.accounts.group_chat;
+        let user = ctx.accounts.user.key();
+
+        let position = room.check_join(&user)?;
+
+        room.invites.swap_remove(position);
+        room.members.push(MemberEntry {
+            member: user,
+            role: MemberRole::Member,
+        });
+
+        emit!(GroupChatJoined {
+            group_chat: room.key(),
+            member: user,
+        });
+
+        msg!("{} joined group chat {}", user, room.name);
+        Ok(())
+    }
+
+    /// 踢出成员：Owner 可以踢任何人，Admin 只能踢普通成员
+    pub fn kick_from_group_chat(ctx: Context<KickFromGroupChat>, member: Pubkey) -> Result<()> {
+        let room = &mut ctx.accounts.group_chat;
+        let user = ctx.accounts.user.key();
+
+        room.check_remove(&user, &member)?;
+
+        room.members.retain(|entry| entry.member != member);
+
+        emit!(GroupChatMemberKicked {
+            group_chat: room.key(),
+            member,
+            kicked_by: user,
+        });
+
+        msg!("{} kicked from group chat {}", member, room.name);
+        Ok(())
+    }
+
+    /// 退出群聊（Owner 不能退出）
+    pub fn leave_group_chat(ctx: Context<LeaveGroupChat>) -> Result<()> {
+        let room = &mut ctx.accounts.group_chat;
+        let user = ctx.accounts.user.key();
+
+        room.check_leave(&user)?;
+
+        room.members.retain(|entry| entry.member != user);
+
+        emit!(GroupChatLeft {
+            group_chat: room.key(),
+            member: user,
+        });
+
+        msg!("{} left group chat {}", user, room.name);
+        Ok(())
+    }
+
+    /// 设置群成员角色（仅 Owner，只能在 Admin 和 Member 之间切换）
+    pub fn set_group_chat_role(
+        ctx: Context<SetGroupChatRole>,
+        member: Pubkey,
+        role: MemberRole,
+    ) -> Result<()> {
+        let room = &mut ctx.accounts.group_chat;
+
+        require!(role != MemberRole::Owner, ErrorCode::InvalidGroupChatRole);
+        let entry = room
+            .members
+            .iter_mut()
+            .find(|entry| entry.member == member)
+            .ok_or(ErrorCode::NotGroupChatMember)?;
+        require!(
+            entry.role != MemberRole::Owner,
+            ErrorCode::InvalidGroupChatRole
+        );
+
+        entry.role = role;
+
+        emit!(GroupChatRoleChanged {
+            group_chat: room.key(),
+            member,
+            role,
+        });
+
+        msg!("{} is now {:?} in group chat {}", member, role, room.name);
+        Ok(())
+    }
+
+    /// 发送群消息（复用 Message 账户，chat_room 字段存群聊地址）
+    pub fn send_group_message(ctx: Context<SendGroupMessage>, content: String) -> Result<()> {
+        require!(
+            !content.is_empty() && content.len() <= MESSAGE_MAX_LEN,
+            ErrorCode::InvalidMessageLength
+        );
+
+        let room = &mut ctx.accounts.group_chat;
+        let message = &mut ctx.accounts.message;
+        let sender = ctx.accounts.sender.key();
+
+        room.check_member(&sender)?;
+        // 被群主拉黑的成员不能在群里发言
+        require!(
+            ctx.accounts.owner_block.data_is_empty(),
+            ErrorCode::UserBlocked
+        );
+
+        message.chat_room = room.key();
+        message.sender = sender;
+        message.content = content.into_bytes();
+        message.encrypted = false;
+        message.message_index = room.message_count;
+        message.timestamp = Clock::get()?.unix_timestamp;
+        message.bump = ctx.bumps.message;
+        message.version = ACCOUNT_VERSION;
+
+        room.message_count += 1;
+        room.last_message_at = message.timestamp;
+
+        reward_pet_activity(
+            &mut ctx.accounts.pet_state,
+            &ctx.accounts.reward_config,
+            RewardAction::MessageSent,
+            message.timestamp,
+        )?;
+        track_quest_progress(
+            &mut ctx.accounts.quest_progress,
+            QuestKind::SendMessages,
+            message.timestamp,
+        );
+
+        emit!(MessageSent {
+            chat_room: message.chat_room,
+            message: message.key(),
+            sender,
+            message_index: message.message_index,
+            timestamp: message.timestamp,
+            encrypted: false,
+        });
+
+        Ok(())
+    }
+
+    // ============================================================================
+    // MIGRATION - 账户迁移 (新功能)
+    // ============================================================================
+
+    pub fn migrate_user_profile(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account::<UserProfile>(&ctx.accounts.account, &ctx.accounts.payer)?;
+        emit!(AccountMigrated {
+            account: ctx.accounts.account.key(),
+            version: ACCOUNT_VERSION,
+        });
+        msg!("User profile migrated to v{}", ACCOUNT_VERSION);
+        Ok(())
+    }
+
+    pub fn migrate_friendship(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account::<Friendship>(&ctx.accounts.account, &ctx.accounts.payer)?;
+        emit!(AccountMigrated {
+            account: ctx.accounts.account.key(),
+            version: ACCOUNT_VERSION,
+        });
+        msg!("Friendship migrated to v{}", ACCOUNT_VERSION);
+        Ok(())
+    }
+
+    pub fn migrate_chat_room(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account::<ChatRoom>(&ctx.accounts.account, &ctx.accounts.payer)?;
+        emit!(AccountMigrated {
+            account: ctx.accounts.account.key(),
+            version: ACCOUNT_VERSION,
+        });
+        msg!("Chat room migrated to v{}", ACCOUNT_VERSION);
+        Ok(())
+    }
+
+    pub fn migrate_message(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account::<Message>(&ctx.accounts.account, &ctx.accounts.payer)?;
+        emit!(AccountMigrated {
+            account: ctx.accounts.account.key(),
+            version: ACCOUNT_VERSION,
+        });
+        msg!("Message migrated to v{}", ACCOUNT_VERSION);
+        Ok(())
+    }
+
+    pub fn migrate_expense_record(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account::<ExpenseRecord>(&ctx.accounts.account, &ctx.accounts.payer)?;
+        emit!(AccountMigrated {
+            account: ctx.accounts.account.key(),
+            version: ACCOUNT_VERSION,
+        });
+        msg!("Expense record migrated to v{}", ACCOUNT_VERSION);
+        Ok(())
+    }
+
+    pub fn migrate_expense_stats(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account::<ExpenseStats>(&ctx.accounts.account, &ctx.accounts.payer)?;
+        emit!(AccountMigrated {
+            account: ctx.accounts.account.key(),
+            version: ACCOUNT_VERSION,
+        });
+        msg!("Expense stats migrated to v{}", ACCOUNT_VERSION);
+        Ok(())
+    }
+
+    pub fn migrate_funding_event(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account::<FundingEvent>(&ctx.accounts.account, &ctx.accounts.payer)?;
+        emit!(AccountMigrated {
+            account: ctx.accounts.account.key(),
+            version: ACCOUNT_VERSION,
+        });
+        msg!("Funding event migrated to v{}", ACCOUNT_VERSION);
+        Ok(())
+    }
+
+    pub fn migrate_application(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account::<Application>(&ctx.accounts.account, &ctx.accounts.payer)?;
+        emit!(AccountMigrated {
+            account: ctx.accounts.account.key(),
+            version: ACCOUNT_VERSION,
+        });
+        msg!("Application migrated to v{}", ACCOUNT_VERSION);
+        Ok(())
+    }
+
+    pub fn migrate_group_split(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account::<GroupSplit>(&ctx.accounts.account, &ctx.accounts.payer)?;
+        emit!(AccountMigrated {
+            account: ctx.accounts.account.key(),
+            version: ACCOUNT_VERSION,
+        });
+        msg!("Group split migrated to v{}", ACCOUNT_VERSION);
+        Ok(())
+    }
+
+    pub fn migrate_split_member(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account::<SplitMember>(&ctx.accounts.account, &ctx.accounts.payer)?;
+        emit!(AccountMigrated {
+            account: ctx.accounts.account.key(),
+            version: ACCOUNT_VERSION,
+        });
+        msg!("Split member migrated to v{}", ACCOUNT_VERSION);
+        Ok(())
+    }
+}
+
+// ============================================================================
+// VALIDATION - 输入校验
+// ============================================================================
+
+pub mod validation {
+    use super::*;
+
+    pub const USERNAME_MIN_CHARS: usize = 3;
+    pub const USERNAME_MAX_CHARS: usize = 20;
+    // 上限按字符数计算；CJK 汉字在 UTF-8 中占 3 bytes
+    pub const USERNAME_MAX_BYTES: usize = USERNAME_MAX_CHARS * 3;
+
+    /// 校验用户名：按字符数计长度，只允许 ASCII 字母数字、`_` `-` `.` 和 CJK 汉字。
+    /// 不开放其它文字（西里尔、希腊、全角字母等），从字符集层面杜绝跨文字的同形字冒充；
+    /// 允许的字符都不存在组合形式，因此 NFC/NFKC 规范化后保持不变。
+    pub fn validate_username(username: &str) -> Result<()> {
+        let char_count = username.chars().count();
+        require!(
+            char_count >= USERNAME_MIN_CHARS,
+            ErrorCode::UsernameTooShort
+        );
+        require!(char_count <= USERNAME_MAX_CHARS, ErrorCode::UsernameTooLong);
+        require!(
+            !username.chars().any(char::is_whitespace),
+            ErrorCode::UsernameContainsWhitespace
+        );
+        require!(
+            !username.chars().any(char::is_control),
+            ErrorCode::UsernameContainsControlCharacter
+        );
+        require!(
+            username.chars().all(is_allowed_username_char),
+            ErrorCode::UsernameInvalidCharacter
+        );
+        require!(
+            !username.starts_with(is_username_separator)
+                && !username.ends_with(is_username_separator),
+            ErrorCode::UsernameSeparatorAtEdge
+        );
+        Ok(())
+    }
+
+    /// 唯一性比较用的规范形式：ASCII 转小写，把形似字母的数字折叠成该字母
+    /// （`1` → `l`，`0` → `o`，`5` → `s` 等），防止 "a1ice"、"b0b" 冒充 "alice"、"bob"。
+    /// 只做单字符折叠，多字符同形（`rn`/`m`）和 `i`/`l` 会误伤 "clara"/"dara"、"mall"/"mali" 这类正常名字
+    pub fn normalize_username(username: &str) -> String {
+        username.chars().map(fold_confusable).collect()
+    }
+
+    fn fold_confusable(c: char) -> char {
+        match c.to_ascii_lowercase() {
+            '1' => 'l',
+            '0' => 'o',
+            '2' => 'z',
+            '3' => 'e',
+            '4' => 'a',
+            '5' => 's',
+            '6' | '8' => 'b',
+            '7' => 't',
+            '9' => 'g',
+            lower => lower,
+        }
+    }
+
+    pub const PROFILE_BIO_MAX_CHARS: usize = 160;
+    pub const PROFILE_BIO_MAX_BYTES: usize = PROFILE_BIO_MAX_CHARS * 3;
+    pub const PROFILE_URI_MAX_LEN: usize = 200;
+    pub const PROFILE_MAX_LINKS: usize = 4;
+    pub const PROFILE_LINK_MAX_LEN: usize = 100;
+
+    // 其它字符串字段的字节上限，账户空间由 #[max_len] 按这些常量推导
+    pub const AVATAR_MAX_LEN: usize = 10;
+    pub const MESSAGE_MAX_LEN: usize = 500;
+    pub const DESCRIPTION_MAX_LEN: usize = 100;
+    pub const TX_SIGNATURE_MAX_LEN: usize = 88;
+    pub const TITLE_MAX_LEN: usize = 64;
+    pub const IPFS_HASH_MAX_LEN: usize = 64;
+
+    const IMAGE_URI_SCHEMES: [&str; 3] = ["ipfs://", "ar://", "https://"];
+
+    /// 简介同样按字符数限制，并拒绝控制字符（换行除外）
+    pub fn validate_bio(bio: &str) -> Result<()> {
+        require!(
+            bio.chars().count() <= PROFILE_BIO_MAX_CHARS && bio.len() <= PROFILE_BIO_MAX_BYTES,
+            ErrorCode::BioTooLong
+        );
+        require!(
+            !bio.chars().any(|c| c.is_control() && c != '\n'),
+            ErrorCode::BioContainsControlCharacter
+        );
+        Ok(())
+    }
+
+    pub fn is_valid_image_uri(uri: &str) -> bool {
+        uri.len() <= PROFILE_URI_MAX_LEN
+            && IMAGE_URI_SCHEMES
+                .iter()
+                .any(|scheme| uri.starts_with(scheme))
+            && is_printable_ascii(uri)
+    }
+
+    pub fn is_valid_link(link: &str) -> bool {
+        link.len() <= PROFILE_LINK_MAX_LEN
+            && link.starts_with("https://")
+            && is_printable_ascii(link)
+    }
+
+    fn is_printable_ascii(value: &str) -> bool {
+        value.bytes().all(|b| b.is_ascii_graphic())
+    }
+
+    /// 用户名 PDA 的 seed：sha256(规范化用户名)，避免 seed 长度受用户名长度限制
+    pub fn username_seed(username: &str) -> [u8; 32] {
+        anchor_lang::solana_program::hash::hash(normalize_username(username).as_bytes()).to_bytes()
+    }
+
+    fn is_allowed_username_char(c: char) -> bool {
+        c.is_ascii_alphanumeric() || is_username_separator(c) || is_cjk_ideograph(c)
+    }
+
+    fn is_username_separator(c: char) -> bool {
+        matches!(c, '_' | '-' | '.')
+    }
+
+    fn is_cjk_ideograph(c: char) -> bool {
+        // CJK 统一汉字 + 扩展 A 区
+        matches!(c, '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}')
+    }
+
+    /// 群聊名称按字节限制长度，不允许控制字符和首尾空白
+    pub fn validate_group_chat_name(name: &str) -> Result<()> {
+        require!(
+            !name.trim().is_empty() && name.len() <= GROUP_CHAT_NAME_MAX_LEN,
+            ErrorCode::InvalidGroupChatName
+        );
+        require!(
+            !name.chars().any(char::is_control) && name.trim() == name,
+            ErrorCode::GroupChatNameInvalidCharacter
+        );
+        Ok(())
+    }
+
+    /// 群聊 topic：默认值表示不关联；否则必须传入对应账户，且是本程序的分账、福利活动或好友圈
+    pub fn validate_group_chat_topic(
+        topic: &Pubkey,
+        topic_account: Option<&AccountInfo>,
+    ) -> Result<()> {
+        if *topic == Pubkey::default() {
+            return Ok(());
+        }
+        let account = topic_account.ok_or(ErrorCode::InvalidGroupChatTopic)?;
+        require!(
+            account.key == topic && account.owner == &crate::ID,
+            ErrorCode::InvalidGroupChatTopic
+        );
+
+        let data = account.try_borrow_data()?;
+        let discriminator = data.get(..8).ok_or(ErrorCode::InvalidGroupChatTopic)?;
+        require!(
+            [
+                <GroupSplit as anchor_lang::Discriminator>::discriminator(),
+                <FundingEvent as anchor_lang::Discriminator>::discriminator(),
+                <Circle as anchor_lang::Discriminator>::discriminator(),
+            ]
+            .iter()
+            .any(|expected| discriminator == expected),
+            ErrorCode::InvalidGroupChatTopic
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod validation_tests {
+    use super::*;
+
+    #[test]
+    fn confusable_usernames_normalize_to_the_same_form() {
+        let pairs = [
+            ("alice", "ALICE"),
+            ("alice", "a1ice"),
+            ("bill", "bi11"),
+            ("bob", "b0b"),
+            ("bob", "8ob"),
+            ("bob", "6ob"),
+            ("sam", "5am"),
+            ("zoe", "2oe"),
+            ("zoe", "z03"),
+            ("anna", "4nna"),
+            ("tom", "7om"),
+            ("greg", "9reg"),
+        ];
+        for (original, impostor) in pairs {
+            assert_eq!(
+                validation::normalize_username(original),
+                validation::normalize_username(impostor),
+                "{impostor} should collide with {original}"
+            );
+            assert_eq!(
+                validation::username_seed(original),
+                validation::username_seed(impostor)
+            );
+        }
+    }
+
+    #[test]
+    fn distinct_usernames_stay_distinct() {
+        let names = [
+            "alice", "alex", "bob", "rob", "mary", "may", "wendy", "vendy", "clara", "dara",
+            "carl", "cari", "mall", "mali", "rnary", "小明", "小红",
+        ];
+        for (i, a) in names.iter().enumerate() {
+            for b in &names[i + 1..] {
+                assert_ne!(
+                    validation::normalize_username(a),
+                    validation::normalize_username(b),
+                    "{a} and {b} should not collide"
+                );
+            }
+        }
+        // 非 ASCII 字符原样保留
+        assert_eq!(validation::normalize_username("小明_01"), "小明_ol");
+    }
+
+    #[test]
+    fn group_chat_name_limits() {
+        assert!(validation::validate_group_chat_name("周末聚餐").is_ok());
+        assert!(validation::validate_group_chat_name("Trip to Bali 🌴").is_ok());
+        assert!(validation::validate_group_chat_name(&"a".repeat(GROUP_CHAT_NAME_MAX_LEN)).is_ok());
+
+        for name in ["", "   ", &"a".repeat(GROUP_CHAT_NAME_MAX_LEN + 1)] {
+            assert_eq!(
+                validation::validate_group_chat_name(name).unwrap_err(),
+                ErrorCode::InvalidGroupChatName.into()
+            );
+        }
+        // 11 个汉字 = 33 bytes
+        assert_eq!(
+            validation::validate_group_chat_name(&"汉".repeat(11)).unwrap_err(),
+            ErrorCode::InvalidGroupChatName.into()
+        );
+        for name in [" padded", "padded ", "line\nbreak", "nul\0", "bell\u{7}"] {
+            assert_eq!(
+                validation::validate_group_chat_name(name).unwrap_err(),
+                ErrorCode::GroupChatNameInvalidCharacter.into()
+            );
+        }
+    }
+
+    fn check_topic(topic: &Pubkey, key: &Pubkey, owner: &Pubkey, data: &mut [u8]) -> Result<()> {
+        let mut lamports = 0;
+        let info = AccountInfo::new(key, false, false, &mut lamports, data, owner, false, 0);
+        validation::validate_group_chat_topic(topic, Some(&info))
+    }
+
+    #[test]
+    fn group_chat_topic_must_be_a_known_program_account() {
+        let topic = Pubkey::new_unique();
+        let circle = <Circle as anchor_lang::Discriminator>::discriminator();
+        let split = <GroupSplit as anchor_lang::Discriminator>::discriminator();
+        let event = <FundingEvent as anchor_lang::Discriminator>::discriminator();
+        let profile = <UserProfile as anchor_lang::Discriminator>::discriminator();
+
+        assert!(validation::validate_group_chat_topic(&Pubkey::default(), None).is_ok());
+        for discriminator in [circle, split, event] {
+            assert!(check_topic(&topic, &topic, &crate::ID, &mut discriminator.clone()).is_ok());
+        }
+
+        let invalid: [(&Pubkey, &Pubkey, &mut [u8]); 4] = [
+            // 传入的账户不是 topic
+            (&Pubkey::new_unique(), &crate::ID, &mut circle.clone()),
+            // 不属于本程序
+            (&topic, &Pubkey::new_unique(), &mut circle.clone()),
+            // 其它类型的账户
+            (&topic, &crate::ID, &mut profile.clone()),
+            (&topic, &crate::ID, &mut []),
+        ];
+        for (key, owner, data) in invalid {
+            assert_eq!(
+                check_topic(&topic, key, owner, data).unwrap_err(),
+                ErrorCode::InvalidGroupChatTopic.into()
+            );
+        }
+        assert_eq!(
+            validation::validate_group_chat_topic(&topic, None).unwrap_err(),
+            ErrorCode::InvalidGroupChatTopic.into()
+        );
+    }
+}
+
+// ============================================================================
+// ACCOUNT STRUCTURES - 账户结构
+// ============================================================================
+
+/// 当前账户布局版本；v0 为没有 version 字段的旧账户
+pub const ACCOUNT_VERSION: u8 = 1;
+/// 每个账户末尾预留的字节，新增字段从这里划出，避免再次 realloc
+pub const ACCOUNT_RESERVED_BYTES: usize = 64;
+
+#[account]
+#[derive(InitSpace)]
+pub struct UserProfile {
+    pub owner: Pubkey,
+    #[max_len(USERNAME_MAX_BYTES)]
+    pub username: String, // max 20 chars, CJK 占 3 bytes
+    #[max_len(AVATAR_MAX_LEN)]
+    pub avatar: String, // e.g., "1.png"
+    pub pet_id: u8,
+    pub friend_count: u32,
+    pub created_at: i64,
+    pub bump: u8,
+    pub version: u8,
+    pub pending_request_count: u32, // 发出和收到的未处理好友请求数
+    pub reserved: [u8; ACCOUNT_RESERVED_BYTES - 4],
+}
+
+impl UserProfile {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+}
+
+// 新增：用户名注册表，PDA 以规范化后的用户名哈希为 seed，保证唯一并支持反查
+#[account]
+#[derive(InitSpace)]
+pub struct UsernameRecord {
+    pub owner: Pubkey,
+    #[max_len(USERNAME_MAX_BYTES)]
+    pub username: String, // 保留用户输入的大小写
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; ACCOUNT_RESERVED_BYTES],
+}
+
+impl UsernameRecord {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+}
+
+/// 占用用户名：新建的记录写入 owner，已被别人占用则报错
+pub fn claim_username(
+    record: &mut Account<UsernameRecord>,
+    owner: Pubkey,
+    username: &str,
+    bump: u8,
+) -> Result<()> {
+    require!(record.owner == Pubkey::default(), ErrorCode::UsernameTaken);
+
+    record.owner = owner;
+    record.username = username.to_string();
+    record.bump = bump;
+    record.version = ACCOUNT_VERSION;
+    Ok(())
+}
+
+/// 好友请求被接受、拒绝、撤回或删除后，双方的未处理请求数各减一
+pub fn release_pending_request(user_a: &mut UserProfile, user_b: &mut UserProfile) {
+    user_a.pending_request_count = user_a.pending_request_count.saturating_sub(1);
+    user_b.pending_request_count = user_b.pending_request_count.saturating_sub(1);
+}
+
+/// 好友分页写入：page 为已有且未满的页，或 index 中下一个新页
+pub fn index_friend(
+    index: &mut Account<FriendIndex>,
+    page: &mut Account<FriendPage>,
+    owner: Pubkey,
+    friend: Pubkey,
+    page_no: u32,
+    index_bump: u8,
+    page_bump: u8,
+) -> Result<()> {
+    if index.owner == Pubkey::default() {
+        index.owner = owner;
+        index.bump = index_bump;
+        index.version = ACCOUNT_VERSION;
+    }
+
+    if page.owner == Pubkey::default() {
+        require!(page_no == index.page_count, ErrorCode::InvalidFriendPage);
+        page.owner = owner;
+        page.page = page_no;
+        page.bump = page_bump;
+        page.version = ACCOUNT_VERSION;
+        index.page_count += 1;
+    }
+
+    require!(
+        page.friends.len() < FRIEND_PAGE_SIZE,
+        ErrorCode::FriendPageFull
+    );
+    page.friends.push(friend);
+    index.friend_count += 1;
+    Ok(())
+}
+
+/// 某个用户的好友 index 和当前好友所在的 page
+pub type FriendPageRefs<'a, 'info> = (
+    Option<&'a mut Account<'info, FriendIndex>>,
+    Option<&'a mut Account<'info, FriendPage>>,
+);
+
+/// 好友分页删除：按 [user_a, user_b] 顺序传入双方的 index 和 page，早于分页的好友关系直接跳过
+pub fn unindex_friendship(
+    friendship: &Friendship,
+    pages: [FriendPageRefs<'_, '_>; 2],
+) -> Result<()> {
+    if !friendship.indexed {
+        return Ok(());
+    }
+
+    let [user_a_pages, user_b_pages] = pages;
+    let entries = [
+        (
+            user_a_pages,
+            friendship.user_a,
+            friendship.user_a_page,
+            friendship.user_b,
+        ),
+        (
+            user_b_pages,
+            friendship.user_b,
+            friendship.user_b_page,
+            friendship.user_a,
+        ),
+    ];
+    for ((index, page), owner, page_no, friend) in entries {
+        let (Some(index), Some(page)) = (index, page) else {
+            return err!(ErrorCode::FriendIndexAccountsMissing);
+        };
+        require!(
+            index.owner == owner && page.owner == owner && page.page == page_no,
+            ErrorCode::InvalidFriendPage
+        );
+        if let Some(position) = page.friends.iter().position(|key| *key == friend) {
+            page.friends.swap_remove(position);
+            index.friend_count = index.friend_count.saturating_sub(1);
+        }
+    }
+    Ok(())
+}
+
+/// 手动关闭 PDA：租金转给 destination，并把账户交还给 System Program
+pub fn close_pda<'info>(
+    account: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+) -> Result<()> {
+    let lamports = account.lamports();
+    **destination.try_borrow_mut_lamports()? += lamports;
+    **account.try_borrow_mut_lamports()? = 0;
+
+    account.assign(&anchor_lang::system_program::ID);
+    account.realloc(0, false)?;
+    Ok(())
+}
+
+// 新增：Profile 扩展资料（avatar 只能放内置图片名，这里支持任意 IPFS/Arweave 图片）
+#[account]
+#[derive(InitSpace)]
+pub struct ProfileDetails {
+    pub owner: Pubkey,
+    #[max_len(PROFILE_BIO_MAX_BYTES)]
+    pub bio: String, // max 160 chars
+    #[max_len(PROFILE_URI_MAX_LEN)]
+    pub image_uri: String,
+    #[max_len(PROFILE_MAX_LINKS, PROFILE_LINK_MAX_LEN)]
+    pub links: Vec<String>,
+    pub updated_at: i64,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; ACCOUNT_RESERVED_BYTES],
+}
+
+impl ProfileDetails {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Friendship {
+    pub user_a: Pubkey,
+    pub user_b: Pubkey,
+    pub requester: Pubkey,
+    pub status: FriendshipStatus,
+    pub created_at: i64,
+    pub bump: u8,
+    pub version: u8,
+    pub indexed: bool,    // 是否已写入双方的好友分页
+    pub user_a_page: u32, // user_a 的好友分页中记录 user_b 的页号
+    pub user_b_page: u32, // user_b 的好友分页中记录 user_a 的页号
+    pub reserved: [u8; ACCOUNT_RESERVED_BYTES - 9],
+}
+
+impl Friendship {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+
+    pub fn is_member(&self, user: &Pubkey) -> bool {
+        self.user_a == *user || self.user_b == *user
+    }
+
+    /// 好友请求的接收方（不是 requester 的那一方）
+    pub fn recipient(&self) -> Pubkey {
+        if self.requester == self.user_a {
+            self.user_b
+        } else {
+            self.user_a
+        }
+    }
+
+    /// 只有接收方可以拒绝待处理的请求
+    pub fn check_reject(&self, user: &Pubkey) -> Result<()> {
+        require!(
+            self.status == FriendshipStatus::Pending,
+            ErrorCode::FriendshipNotPending
+        );
+        require!(*user == self.recipient(), ErrorCode::Unauthorized);
+        Ok(())
+    }
+
+    /// 只有请求方可以撤回待处理的请求
+    pub fn check_cancel(&self, user: &Pubkey) -> Result<()> {
+        require!(
+            self.status == FriendshipStatus::Pending,
+            ErrorCode::FriendshipNotPending
+        );
+        require!(*user == self.requester, ErrorCode::Unauthorized);
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum FriendshipStatus {
+    Pending,
+    Accepted,
+}
+
+#[cfg(test)]
+mod friend_request_tests {
+    use super::*;
+
+    /// requester 分别是 user_a 和 user_b 两种排列
+    fn pending_requests() -> Vec<Friendship> {
+        let (low, high) = {
+            let (x, y) = (Pubkey::new_unique(), Pubkey::new_unique());
+            (x.min(y), x.max(y))
+        };
+        [low, high]
+            .into_iter()
+            .map(|requester| Friendship {
+                user_a: low,
+                user_b: high,
+                requester,
+                status: FriendshipStatus::Pending,
+                created_at: 0,
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                indexed: false,
+                user_a_page: 0,
+                user_b_page: 0,
+                reserved: [0; ACCOUNT_RESERVED_BYTES - 9],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn recipient_is_the_other_member() {
+        for friendship in pending_requests() {
+            let recipient = friendship.recipient();
+            assert_ne!(recipient, friendship.requester);
+            assert!(friendship.is_member(&recipient));
+        }
+    }
+
+    #[test]
+    fn only_recipient_can_reject() {
+        for friendship in pending_requests() {
+            assert!(friendship.check_reject(&friendship.recipient()).is_ok());
+            assert_eq!(
+                friendship.check_reject(&friendship.requester).unwrap_err(),
+                ErrorCode::Unauthorized.into()
+            );
+            assert_eq!(
+                friendship.check_reject(&Pubkey::new_unique()).unwrap_err(),
+                ErrorCode::Unauthorized.into()
+            );
+        }
+    }
+
+    #[test]
+    fn only_requester_can_cancel() {
+        for friendship in pending_requests() {
+            assert!(friendship.check_cancel(&friendship.requester).is_ok());
+            assert_eq!(
+                friendship
+                    .check_cancel(&friendship.recipient())
+                    .unwrap_err(),
+                ErrorCode::Unauthorized.into()
+            );
+            assert_eq!(
+                friendship.check_cancel(&Pubkey::new_unique()).unwrap_err(),
+                ErrorCode::Unauthorized.into()
+            );
+        }
+    }
+
+    #[test]
+    fn accepted_friendship_cannot_be_rejected_or_cancelled() {
+        for mut friendship in pending_requests() {
+            friendship.status = FriendshipStatus::Accepted;
+            assert_eq!(
+                friendship
+                    .check_reject(&friendship.recipient())
+                    .unwrap_err(),
+                ErrorCode::FriendshipNotPending.into()
+            );
+            assert_eq!(
+                friendship.check_cancel(&friendship.requester).unwrap_err(),
+                ErrorCode::FriendshipNotPending.into()
+            );
+        }
+    }
+}
+
+// 新增：好友索引，PDA = ["friend_index", owner]，记录分页数量
+#[account]
+#[derive(InitSpace)]
+pub struct FriendIndex {
+    pub owner: Pubkey,
+    pub friend_count: u32, // 已写入分页的好友数
+    pub page_count: u32,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; ACCOUNT_RESERVED_BYTES],
+}
+
+impl FriendIndex {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+}
+
+// 新增：好友分页，PDA = ["friend_page", owner, page (u32 LE)]，客户端按 0..page_count 依次读取
+#[account]
+#[derive(InitSpace)]
+pub struct FriendPage {
+    pub owner: Pubkey,
+    pub page: u32,
+    #[max_len(FRIEND_PAGE_SIZE)]
+    pub friends: Vec<Pubkey>,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; ACCOUNT_RESERVED_BYTES],
+}
+
+impl FriendPage {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+}
+
+pub const FRIEND_PAGE_SIZE: usize = 32;
+
+// 新增：拉黑记录，PDA = ["block", blocker, blocked]，存在即生效
+#[account]
+#[derive(InitSpace)]
+pub struct UserBlock {
+    pub blocker: Pubkey,
+    pub blocked: Pubkey,
+    pub created_at: i64,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; ACCOUNT_RESERVED_BYTES],
+}
+
+impl UserBlock {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ChatRoom {
+    pub user_a: Pubkey,
+    pub user_b: Pubkey,
+    pub message_count: u64,
+    pub last_message_at: i64,
+    pub bump: u8,
+    pub version: u8,
+    pub page_count: u32,     // 已创建的 ChatPage 数量，当前页 = page_count - 1
+    pub oldest_page: u32,    // 最早未回收的页
+    pub retained_pages: u16, // 保留最近多少页，0 = 全部保留
+    pub compressed: bool,    // 已启用压缩聊天记录（Merkle tree）
+    pub tree_root: [u8; 32],
+    pub tree_leaf_count: u64,
+    pub reserved: [u8; ACCOUNT_RESERVED_BYTES - 51],
+}
 
 impl ChatRoom {
-    pub const LEN: usize = 8 + 32 + 32 + 8 + 8 + 1;
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+
+    /// 页内记录的发送者槽位：0 = user_a，1 = user_b
+    pub fn member_slot(&self, user: &Pubkey) -> u8 {
+        if *user == self.user_a {
+            0
+        } else {
+            1
+        }
+    }
+
+    /// 聊天室中另一位成员
+    pub fn other_member(&self, user: &Pubkey) -> Pubkey {
+        if *user == self.user_a {
+            self.user_b
+        } else {
+            self.user_a
+        }
+    }
+
+    /// 新的保留页数比当前少（0 = 全部保留，视为最多）
+    pub fn retention_shrinks(&self, retained_pages: u16) -> bool {
+        retained_pages != 0 && (self.retained_pages == 0 || retained_pages < self.retained_pages)
+    }
+
+    /// 成员可以单方面放宽保留策略，缩短时另一位成员也必须签名
+    pub fn check_retention_change(
+        &self,
+        user: &Pubkey,
+        peer: Option<&Pubkey>,
+        retained_pages: u16,
+    ) -> Result<()> {
+        require!(
+            *user == self.user_a || *user == self.user_b,
+            ErrorCode::NotChatRoomMember
+        );
+        if self.retention_shrinks(retained_pages) {
+            require!(
+                peer == Some(&self.other_member(user)),
+                ErrorCode::ChatRetentionNeedsBothMembers
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod chat_retention_tests {
+    use super::*;
+
+    fn chat_room(retained_pages: u16) -> ChatRoom {
+        ChatRoom {
+            user_a: Pubkey::new_unique(),
+            user_b: Pubkey::new_unique(),
+            message_count: 0,
+            last_message_at: 0,
+            bump: 255,
+            version: ACCOUNT_VERSION,
+            page_count: 0,
+            oldest_page: 0,
+            retained_pages,
+            compressed: false,
+            tree_root: [0; 32],
+            tree_leaf_count: 0,
+            reserved: [0; ACCOUNT_RESERVED_BYTES - 51],
+        }
+    }
+
+    #[test]
+    fn shrinking_is_relative_to_keep_all() {
+        let keep_all = chat_room(0);
+        assert!(keep_all.retention_shrinks(1));
+        assert!(keep_all.retention_shrinks(u16::MAX));
+        assert!(!keep_all.retention_shrinks(0));
+
+        let keep_ten = chat_room(10);
+        assert!(keep_ten.retention_shrinks(9));
+        assert!(!keep_ten.retention_shrinks(10));
+        assert!(!keep_ten.retention_shrinks(11));
+        assert!(!keep_ten.retention_shrinks(0));
+    }
+
+    #[test]
+    fn either_member_can_extend_retention() {
+        let room = chat_room(10);
+        assert!(room.check_retention_change(&room.user_a, None, 20).is_ok());
+        assert!(room.check_retention_change(&room.user_b, None, 0).is_ok());
+        assert_eq!(
+            room.check_retention_change(&Pubkey::new_unique(), None, 20)
+                .unwrap_err(),
+            ErrorCode::NotChatRoomMember.into()
+        );
+    }
+
+    #[test]
+    fn shrinking_requires_both_members() {
+        let room = chat_room(10);
+        let outsider = Pubkey::new_unique();
+
+        assert_eq!(
+            room.check_retention_change(&room.user_a, None, 5)
+                .unwrap_err(),
+            ErrorCode::ChatRetentionNeedsBothMembers.into()
+        );
+        assert_eq!(
+            room.check_retention_change(&room.user_a, Some(&room.user_a), 5)
+                .unwrap_err(),
+            ErrorCode::ChatRetentionNeedsBothMembers.into()
+        );
+        assert_eq!(
+            room.check_retention_change(&room.user_a, Some(&outsider), 5)
+                .unwrap_err(),
+            ErrorCode::ChatRetentionNeedsBothMembers.into()
+        );
+        assert!(room
+            .check_retention_change(&room.user_a, Some(&room.user_b), 5)
+            .is_ok());
+        assert!(room
+            .check_retention_change(&room.user_b, Some(&room.user_a), 5)
+            .is_ok());
+
+        // 从全部保留改成有限页数同样需要双方签名
+        let keep_all = chat_room(0);
+        assert_eq!(
+            keep_all
+                .check_retention_change(&keep_all.user_b, None, 100)
+                .unwrap_err(),
+            ErrorCode::ChatRetentionNeedsBothMembers.into()
+        );
+    }
+}
+
+// 新增：X25519 加密公钥，PDA = ["encryption_key", owner]
+#[account]
+#[derive(InitSpace)]
+pub struct EncryptionKey {
+    pub owner: Pubkey,
+    pub public_key: [u8; 32],
+    pub key_version: u32, // 从 1 开始，每次轮换 + 1
+    pub updated_at: i64,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; ACCOUNT_RESERVED_BYTES],
+}
+
+impl EncryptionKey {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+}
+
+/// NaCl box (X25519 + XSalsa20-Poly1305) 的 nonce 和认证标签长度
+pub const ENCRYPTION_NONCE_LEN: usize = 24;
+pub const ENCRYPTION_TAG_LEN: usize = 16;
+pub const MESSAGE_CIPHERTEXT_MAX_LEN: usize = MESSAGE_MAX_LEN + ENCRYPTION_TAG_LEN;
+
+// 新增：聊天全局配置（全局唯一）
+#[account]
+#[derive(InitSpace)]
+pub struct ChatConfig {
+    pub authority: Pubkey,
+    pub require_friendship: bool, // 创建聊天室前要求双方已是好友
+    pub bump: u8,
+    pub version: u8,
+    pub edit_window_secs: i64, // 消息发出后可编辑的时长
+    pub reserved: [u8; ACCOUNT_RESERVED_BYTES - 8],
+}
+
+impl ChatConfig {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+
+    /// 读取可能尚未初始化的全局配置
+    pub fn load(info: &AccountInfo) -> Result<Option<Self>> {
+        if info.data_is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self::try_deserialize(
+            &mut &info.try_borrow_data()?[..],
+        )?))
+    }
+}
+
+/// 未初始化 ChatConfig 时的默认编辑时限（15 分钟）
+pub const DEFAULT_MESSAGE_EDIT_WINDOW_SECS: i64 = 15 * 60;
+
+#[account]
+#[derive(InitSpace)]
+pub struct Message {
+    pub chat_room: Pubkey,
+    pub sender: Pubkey,
+    // 明文为 UTF-8，加密消息为密文；与旧的 String 布局 Borsh 编码一致
+    #[max_len(MESSAGE_CIPHERTEXT_MAX_LEN)]
+    pub content: Vec<u8>,
+    pub message_index: u64,
+    pub timestamp: i64,
+    pub bump: u8,
+    pub version: u8,
+    pub encrypted: bool,
+    pub nonce: [u8; ENCRYPTION_NONCE_LEN],
+    pub sender_key_version: u32,
+    pub recipient_key_version: u32,
+    pub edited_at: i64, // 0 = 未编辑
+    pub edit_count: u16,
+    pub reserved: [u8; ACCOUNT_RESERVED_BYTES - 43],
+}
+
+impl Message {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+}
+
+// 新增：分页消息缓冲区，PDA = ["chat_page", chat_room, page_index]
+// 每条记录：sender_slot (1) + message_index (8) + timestamp (8) + len (2) + content
+#[account]
+#[derive(InitSpace)]
+pub struct ChatPage {
+    pub chat_room: Pubkey,
+    pub page_index: u32,
+    pub payer: Pubkey, // 回收时租金退还给创建该页的人
+    pub entry_count: u16,
+    pub last_message_at: i64,
+    #[max_len(CHAT_PAGE_DATA_LEN)]
+    pub data: Vec<u8>,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; ACCOUNT_RESERVED_BYTES],
+}
+
+impl ChatPage {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+
+    /// 追加一条消息，放不下时返回 ChatPageFull，客户端应换到下一页
+    pub fn append(
+        &mut self,
+        sender_slot: u8,
+        message_index: u64,
+        timestamp: i64,
+        content: &[u8],
+    ) -> Result<()> {
+        require!(
+            self.data.len() + CHAT_PAGE_ENTRY_HEADER_LEN + content.len() <= CHAT_PAGE_DATA_LEN,
+            ErrorCode::ChatPageFull
+        );
+
+        self.data.push(sender_slot);
+        self.data.extend_from_slice(&message_index.to_le_bytes());
+        self.data.extend_from_slice(&timestamp.to_le_bytes());
+        self.data
+            .extend_from_slice(&(content.len() as u16).to_le_bytes());
+        self.data.extend_from_slice(content);
+        self.entry_count += 1;
+        self.last_message_at = timestamp;
+        Ok(())
+    }
+}
+
+pub const CHAT_PAGE_DATA_LEN: usize = 4096;
+pub const CHAT_PAGE_ENTRY_HEADER_LEN: usize = 19;
+
+#[cfg(test)]
+mod chat_page_packing_tests {
+    use super::*;
+
+    /// rent-exempt = (bytes + 128) * 6960 lamports
+    fn rent(bytes: usize) -> u64 {
+        (bytes as u64 + 128) * 6960
+    }
+
+    fn empty_page() -> ChatPage {
+        ChatPage {
+            chat_room: Pubkey::new_unique(),
+            page_index: 0,
+            payer: Pubkey::new_unique(),
+            entry_count: 0,
+            last_message_at: 0,
+            data: Vec::new(),
+            bump: 255,
+            version: ACCOUNT_VERSION,
+            reserved: [0; ACCOUNT_RESERVED_BYTES],
+        }
+    }
+
+    /// ChatPage::LEN 减去定长字段：discriminator + chat_room + page_index + payer
+    /// + entry_count + last_message_at + data 长度前缀 + bump + version + reserved
+    fn page_data_len() -> usize {
+        ChatPage::LEN - (8 + 32 + 4 + 32 + 2 + 8 + 4 + 1 + 1 + ACCOUNT_RESERVED_BYTES)
+    }
+
+    #[test]
+    fn page_capacity_is_derived_from_account_len() {
+        assert_eq!(page_data_len(), CHAT_PAGE_DATA_LEN);
+        assert_eq!(ChatPage::LEN, 4252);
+        assert_eq!(Message::LEN, 674);
+    }
+
+    /// 与 SOLANA_CONTRACT_DESIGN.md 里租金对比表保持一致
+    #[test]
+    fn messages_per_page_match_design_doc() {
+        // (内容长度, 每页条数, 每条租金 / 10^-5 SOL)
+        let table = [(20, 105, 29), (80, 41, 74), (200, 18, 169), (500, 7, 435)];
+
+        for (content_len, per_page, rent_per_message) in table {
+            assert_eq!(
+                page_data_len() / (CHAT_PAGE_ENTRY_HEADER_LEN + content_len),
+                per_page
+            );
+            assert_eq!(
+                rent(ChatPage::LEN) / per_page as u64 / 10_000,
+                rent_per_message
+            );
+
+            // 实际追加到满页，条数一致，下一条返回 ChatPageFull
+            let mut page = empty_page();
+            let content = vec![b'x'; content_len];
+            let mut index = 0;
+            while page.append(0, index, 0, &content).is_ok() {
+                index += 1;
+            }
+            assert_eq!(page.entry_count as usize, per_page);
+            assert_eq!(
+                page.append(0, index, 0, &content).unwrap_err(),
+                ErrorCode::ChatPageFull.into()
+            );
+        }
+
+        assert_eq!(rent(Message::LEN) / 10_000, 558);
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ExpenseRecord {
+    pub owner: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub category: ExpenseCategory,
+    #[max_len(DESCRIPTION_MAX_LEN)]
+    pub description: String,
+    pub timestamp: i64,
+    #[max_len(TX_SIGNATURE_MAX_LEN)]
+    pub tx_signature: String,
+    pub record_index: u64,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; ACCOUNT_RESERVED_BYTES],
+}
+
+impl ExpenseRecord {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ExpenseStats {
+    pub owner: Pubkey,
+    pub total_spent: u64,
+    pub record_count: u64,
+    pub dining_total: u64,
+    pub shopping_total: u64,
+    pub entertainment_total: u64,
+    pub travel_total: u64,
+    pub gifts_total: u64,
+    pub bills_total: u64,
+    pub other_total: u64,
+    pub last_updated: i64,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; ACCOUNT_RESERVED_BYTES],
+}
+
+impl ExpenseStats {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug, InitSpace)]
+pub enum ExpenseCategory {
+    Dining,
+    Shopping,
+    Entertainment,
+    Travel,
+    Gifts,
+    Bills,
+    Other,
+}
+
+// 新增：福利活动账户
+#[account]
+#[derive(InitSpace)]
+pub struct FundingEvent {
+    pub creator: Pubkey,
+    #[max_len(TITLE_MAX_LEN)]
+    pub title: String,
+    pub total_amount: u64,
+    pub remaining_amount: u64,
+    pub deadline: i64,
+    #[max_len(IPFS_HASH_MAX_LEN)]
+    pub ipfs_hash: String,
+    pub status: EventStatus,
+    pub created_at: i64,
+    pub application_count: u32,
+    pub approved_count: u32,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; ACCOUNT_RESERVED_BYTES],
+}
+
+impl FundingEvent {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+}
+
+// 新增：申请账户
+#[account]
+#[derive(InitSpace)]
+pub struct Application {
+    pub event: Pubkey,
+    pub applicant: Pubkey,
+    pub requested_amount: u64,
+    pub approved_amount: u64,
+    #[max_len(IPFS_HASH_MAX_LEN)]
+    pub ipfs_hash: String,
+    pub status: ApplicationStatus,
+    pub applied_at: i64,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; ACCOUNT_RESERVED_BYTES],
+}
+
+impl Application {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+}
+
+// 新增：活动状态
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum EventStatus {
+    Active,
+    Closed,
+}
+
+// 新增：申请状态
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum ApplicationStatus {
+    Pending,
+    Approved,
+    Rejected,
+    Paid,
+}
+
+// 新增：分账群组账户
+#[account]
+#[derive(InitSpace)]
+pub struct GroupSplit {
+    pub creator: Pubkey,
+    #[max_len(TITLE_MAX_LEN)]
+    pub title: String,
+    pub total_amount: u64,
+    pub member_count: u8,
+    pub amount_per_person: u64,
+    #[max_len(IPFS_HASH_MAX_LEN)]
+    pub ipfs_hash: String,
+    pub created_at: i64,
+    pub settled_count: u32,
+    pub status: SplitStatus,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; ACCOUNT_RESERVED_BYTES],
+}
+
+impl GroupSplit {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+}
+
+// 新增：分账成员账户
+#[account]
+#[derive(InitSpace)]
+pub struct SplitMember {
+    pub split: Pubkey,
+    pub member: Pubkey,
+    pub amount_owed: u64,
+    pub paid: bool,
+    pub paid_at: i64,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; ACCOUNT_RESERVED_BYTES],
+}
+
+impl SplitMember {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+}
+
+// 新增：分账状态
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum SplitStatus {
+    Active,
+    Settled,
+    Closed,
+}
+
+// 新增：宠物状态账户
+#[account]
+#[derive(InitSpace)]
+pub struct PetState {
+    pub owner: Pubkey,
+    pub pet_id: u8,
+    pub level: u8,
+    pub xp: u32,
+    pub happiness: u8,
+    pub energy: u8,
+    pub last_fed: i64,
+    pub last_played: i64,
+    pub last_decay_at: i64,
+    pub total_interactions: u32,
+    pub created_at: i64,
+    pub reward_day: i64,
+    pub daily_xp: [u32; REWARD_ACTION_COUNT],
+    pub stage: PetStage,
+    pub evolved_at: i64,
+    pub nft_mint: Pubkey, // 未铸造时为 Pubkey::default()
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; ACCOUNT_RESERVED_BYTES],
+}
+
+impl PetState {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+
+    /// 按经过的整小时结算：超过 12 小时未喂食会掉快乐值，能量随时间恢复
+    pub fn apply_decay(&mut self, now: i64) {
+        let hours = now.saturating_sub(self.last_decay_at) / SECONDS_PER_HOUR;
+        if hours <= 0 {
+            return;
+        }
+
+        let window_end = self.last_decay_at + hours * SECONDS_PER_HOUR;
+        let hungry_from = self
+            .last_fed
+            .saturating_add(PET_HUNGER_GRACE)
+            .max(self.last_decay_at);
+        let hungry_hours = window_end.saturating_sub(hungry_from).max(0) / SECONDS_PER_HOUR;
+
+        self.happiness = self
+            .happiness
+            .saturating_sub(clamp_stat(hungry_hours * PET_HUNGER_DECAY_PER_HOUR));
+        self.energy = self
+            .energy
+            .saturating_add(clamp_stat(hours * PET_ENERGY_REGEN_PER_HOUR))
+            .min(PET_STAT_MAX);
+
+        // 只推进整小时，避免频繁调用吞掉不足一小时的部分
+        self.last_decay_at = window_end;
+    }
+
+    /// 增加经验值并重新计算等级，返回是否升级
+    pub fn add_xp(&mut self, amount: u32) -> bool {
+        let old_level = self.level;
+        self.xp = self.xp.saturating_add(amount);
+        self.level = level_for_xp(self.xp);
+        self.level > old_level
+    }
+
+    pub fn multipliers(&self) -> PetMultipliers {
+        pet_species(self.pet_id).multipliers
+    }
+
+    /// 按奖励表发放活动经验，每种活动每个 UTC 日有上限，返回实际获得的经验
+    pub fn award_activity_xp(
+        &mut self,
+        config: &RewardConfig,
+        action: RewardAction,
+        now: i64,
+    ) -> u32 {
+        let day = now.div_euclid(SECONDS_PER_DAY);
+        if day != self.reward_day {
+            self.reward_day = day;
+            self.daily_xp = [0; REWARD_ACTION_COUNT];
+        }
+
+        let slot = action as usize;
+        let reward = apply_bps(
+            config.xp_rewards[slot],
+            self.multipliers().activity_bps[slot],
+        );
+        let remaining = config.daily_caps[slot].saturating_sub(self.daily_xp[slot]);
+        let earned = reward.min(remaining);
+
+        self.daily_xp[slot] += earned;
+        self.add_xp(earned);
+        earned
+    }
+}
+
+// 新增：宠物进化形态
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum PetStage {
+    Baby,
+    Teen,
+    Adult,
+    Legendary,
+}
+
+impl PetStage {
+    pub fn next(self) -> Option<PetStage> {
+        match self {
+            PetStage::Baby => Some(PetStage::Teen),
+            PetStage::Teen => Some(PetStage::Adult),
+            PetStage::Adult => Some(PetStage::Legendary),
+            PetStage::Legendary => None,
+        }
+    }
+
+    /// 进入该形态所需的门槛
+    pub fn requirement(self) -> EvolutionRequirement {
+        match self {
+            PetStage::Baby => EvolutionRequirement::new(1, 0, 0),
+            PetStage::Teen => EvolutionRequirement::new(3, 1, 0),
+            PetStage::Adult => EvolutionRequirement::new(6, 3, 10),
+            PetStage::Legendary => EvolutionRequirement::new(PET_MAX_LEVEL, 10, 50),
+        }
+    }
+}
+
+pub struct EvolutionRequirement {
+    pub min_level: u8,
+    pub min_friends: u32,
+    pub min_expense_records: u64,
+}
+
+impl EvolutionRequirement {
+    const fn new(min_level: u8, min_friends: u32, min_expense_records: u64) -> Self {
+        Self {
+            min_level,
+            min_friends,
+            min_expense_records,
+        }
+    }
+
+    pub fn check(&self, level: u8, friends: u32, expense_records: u64) -> Result<()> {
+        require!(level >= self.min_level, ErrorCode::EvolutionLevelTooLow);
+        require!(
+            friends >= self.min_friends,
+            ErrorCode::EvolutionNeedsFriends
+        );
+        require!(
+            expense_records >= self.min_expense_records,
+            ErrorCode::EvolutionNeedsExpenses
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod pet_species_tests {
+    use super::*;
+
+    fn pet(pet_id: u8) -> PetState {
+        PetState {
+            owner: Pubkey::new_unique(),
+            pet_id,
+            level: 1,
+            xp: 0,
+            happiness: PET_STAT_MAX,
+            energy: PET_STAT_MAX,
+            last_fed: 0,
+            last_played: 0,
+            last_decay_at: 0,
+            total_interactions: 0,
+            created_at: 0,
+            reward_day: 0,
+            daily_xp: [0; REWARD_ACTION_COUNT],
+            stage: PetStage::Baby,
+            evolved_at: 0,
+            nft_mint: Pubkey::default(),
+            bump: 255,
+            version: ACCOUNT_VERSION,
+            reserved: [0; ACCOUNT_RESERVED_BYTES],
+        }
+    }
+
+    fn config(xp: u32, cap: u32) -> RewardConfig {
+        RewardConfig {
+            authority: Pubkey::new_unique(),
+            xp_rewards: [xp; REWARD_ACTION_COUNT],
+            daily_caps: [cap; REWARD_ACTION_COUNT],
+            bump: 255,
+            version: ACCOUNT_VERSION,
+            reserved: [0; ACCOUNT_RESERVED_BYTES],
+        }
+    }
+
+    #[test]
+    fn species_lookup_covers_all_ids() {
+        let names: Vec<&str> = (1..=10).map(|id| pet_species(id).name).collect();
+        assert_eq!(
+            names,
+            ["Dragon", "Cat", "Dog", "Pig", "Monkey", "Cow", "Rabbit", "Tiger", "Goat", "Mouse"]
+        );
+
+        for id in [0, 11, u8::MAX] {
+            let species = pet_species(id);
+            assert_eq!(species.name, "Unknown");
+            assert_eq!(
+                species.multipliers.activity_bps,
+                [BPS_BASE; REWARD_ACTION_COUNT]
+            );
+            assert_eq!(species.multipliers.care_bps, BPS_BASE);
+            assert_eq!(species.multipliers.quest_bps, BPS_BASE);
+        }
+    }
+
+    #[test]
+    fn apply_bps_rounds_down_and_saturates() {
+        assert_eq!(apply_bps(100, BPS_BASE), 100);
+        assert_eq!(apply_bps(10, 11_500), 11);
+        assert_eq!(apply_bps(10, 15_000), 15);
+        assert_eq!(apply_bps(0, 15_000), 0);
+        assert_eq!(apply_bps(u32::MAX, 15_000), u32::MAX);
+    }
+
+    #[test]
+    fn activity_xp_differs_by_species() {
+        let config = config(100, 1_000);
+        let earned = |pet_id: u8, action: RewardAction| {
+            pet(pet_id).award_activity_xp(&config, action, SECONDS_PER_DAY)
+        };
+
+        // Dragon / Pig / Rabbit 全局加成
+        assert_eq!(earned(1, RewardAction::MessageSent), 115);
+        assert_eq!(earned(4, RewardAction::MessageSent), 110);
+        assert_eq!(earned(7, RewardAction::SplitPaid), 110);
+        // 单项加成只影响对应活动
+        assert_eq!(earned(2, RewardAction::ExpenseRecorded), 120);
+        assert_eq!(earned(2, RewardAction::MessageSent), 100);
+        assert_eq!(earned(3, RewardAction::FriendAccepted), 120);
+        assert_eq!(earned(3, RewardAction::ExpenseRecorded), 100);
+        assert_eq!(earned(8, RewardAction::SplitPaid), 130);
+        assert_eq!(earned(9, RewardAction::ExpenseRecorded), 110);
+        assert_eq!(earned(10, RewardAction::MessageSent), 150);
+        assert_eq!(earned(10, RewardAction::FriendAccepted), 100);
+        // Monkey / Cow 只加成任务和照顾，活动经验不变
+        assert_eq!(earned(5, RewardAction::MessageSent), 100);
+        assert_eq!(earned(6, RewardAction::MessageSent), 100);
+        assert_eq!(pet(5).multipliers().quest_bps, 14_000);
+        assert_eq!(pet(6).multipliers().care_bps, 12_000);
+        // 未知种类按 1.0x
+        assert_eq!(earned(0, RewardAction::MessageSent), 100);
+    }
+
+    #[test]
+    fn daily_cap_applies_after_species_bonus() {
+        let config = config(100, 250);
+        let mut mouse = pet(10);
+        let day = 3 * SECONDS_PER_DAY;
+
+        assert_eq!(
+            mouse.award_activity_xp(&config, RewardAction::MessageSent, day),
+            150
+        );
+        assert_eq!(
+            mouse.award_activity_xp(&config, RewardAction::MessageSent, day),
+            100
+        );
+        assert_eq!(
+            mouse.award_activity_xp(&config, RewardAction::MessageSent, day),
+            0
+        );
+        // 其它活动有独立的上限
+        assert_eq!(
+            mouse.award_activity_xp(&config, RewardAction::FriendAccepted, day),
+            100
+        );
+        // 下一个 UTC 日重置
+        assert_eq!(
+            mouse.award_activity_xp(&config, RewardAction::MessageSent, day + SECONDS_PER_DAY),
+            150
+        );
+        assert_eq!(mouse.xp, 500);
+    }
+
+    #[test]
+    fn level_thresholds() {
+        assert_eq!(level_for_xp(0), 1);
+        assert_eq!(level_for_xp(99), 1);
+        assert_eq!(level_for_xp(100), 2);
+        assert_eq!(level_for_xp(249), 2);
+        assert_eq!(level_for_xp(250), 3);
+        assert_eq!(level_for_xp(1_999), 5);
+        assert_eq!(level_for_xp(2_000), 6);
+        assert_eq!(level_for_xp(11_999), 9);
+        assert_eq!(level_for_xp(12_000), PET_MAX_LEVEL);
+        assert_eq!(level_for_xp(u32::MAX), PET_MAX_LEVEL);
+    }
+
+    #[test]
+    fn evolution_thresholds() {
+        let teen = PetStage::Teen.requirement();
+        assert!(teen.check(3, 1, 0).is_ok());
+        assert_eq!(
+            teen.check(2, 1, 0).unwrap_err(),
+            ErrorCode::EvolutionLevelTooLow.into()
+        );
+        assert_eq!(
+            teen.check(3, 0, 0).unwrap_err(),
+            ErrorCode::EvolutionNeedsFriends.into()
+        );
+
+        let adult = PetStage::Adult.requirement();
+        assert!(adult.check(6, 3, 10).is_ok());
+        assert_eq!(
+            adult.check(5, 3, 10).unwrap_err(),
+            ErrorCode::EvolutionLevelTooLow.into()
+        );
+        assert_eq!(
+            adult.check(6, 2, 10).unwrap_err(),
+            ErrorCode::EvolutionNeedsFriends.into()
+        );
+        assert_eq!(
+            adult.check(6, 3, 9).unwrap_err(),
+            ErrorCode::EvolutionNeedsExpenses.into()
+        );
+
+        let legendary = PetStage::Legendary.requirement();
+        assert!(legendary.check(PET_MAX_LEVEL, 10, 50).is_ok());
+        assert_eq!(
+            legendary.check(PET_MAX_LEVEL - 1, 10, 50).unwrap_err(),
+            ErrorCode::EvolutionLevelTooLow.into()
+        );
+        assert_eq!(
+            legendary.check(PET_MAX_LEVEL, 9, 50).unwrap_err(),
+            ErrorCode::EvolutionNeedsFriends.into()
+        );
+        assert_eq!(
+            legendary.check(PET_MAX_LEVEL, 10, 49).unwrap_err(),
+            ErrorCode::EvolutionNeedsExpenses.into()
+        );
+    }
+
+    #[test]
+    fn evolution_stages_advance_in_order() {
+        assert_eq!(PetStage::Baby.next(), Some(PetStage::Teen));
+        assert_eq!(PetStage::Teen.next(), Some(PetStage::Adult));
+        assert_eq!(PetStage::Adult.next(), Some(PetStage::Legendary));
+        assert_eq!(PetStage::Legendary.next(), None);
+    }
+}
+
+// 新增：经验奖励表（全局唯一）
+#[account]
+#[derive(InitSpace)]
+pub struct RewardConfig {
+    pub authority: Pubkey,
+    pub xp_rewards: [u32; REWARD_ACTION_COUNT],
+    pub daily_caps: [u32; REWARD_ACTION_COUNT],
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; ACCOUNT_RESERVED_BYTES],
+}
+
+impl RewardConfig {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+}
+
+// 新增：可获得经验的活动，顺序即奖励表下标
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RewardAction {
+    FriendAccepted,
+    MessageSent,
+    ExpenseRecorded,
+    SplitPaid,
+}
+
+pub const REWARD_ACTION_COUNT: usize = 4;
+
+/// 如果调用者附带了宠物账户，则按奖励表给宠物加经验
+pub fn reward_pet_activity(
+    pet_state: &mut Option<Account<PetState>>,
+    reward_config: &Option<Account<RewardConfig>>,
+    action: RewardAction,
+    now: i64,
+) -> Result<()> {
+    if let Some(pet) = pet_state.as_mut() {
+        let config = reward_config
+            .as_ref()
+            .ok_or(ErrorCode::RewardConfigMissing)?;
+        let earned = pet.award_activity_xp(config, action, now);
+        emit!(PetXpEarned {
+            owner: pet.owner,
+            action,
+            xp_earned: earned,
+            level: pet.level,
+            xp: pet.xp,
+        });
+        msg!("Pet earned {} XP for {:?}", earned, action);
+    }
+    Ok(())
+}
+
+// 新增：宠物种类加成，以万分比 (basis points) 表示，10000 = 1.0x
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PetMultipliers {
+    pub activity_bps: [u16; REWARD_ACTION_COUNT], // 按 RewardAction 下标
+    pub care_bps: u16,                            // 喂食 / 玩耍
+    pub quest_bps: u16,                           // 每日任务奖励
+}
+
+pub struct PetSpecies {
+    pub name: &'static str,
+    pub multipliers: PetMultipliers,
+}
+
+impl PetSpecies {
+    const fn new(
+        name: &'static str,
+        activity_bps: [u16; REWARD_ACTION_COUNT],
+        care_bps: u16,
+        quest_bps: u16,
+    ) -> Self {
+        Self {
+            name,
+            multipliers: PetMultipliers {
+                activity_bps,
+                care_bps,
+                quest_bps,
+            },
+        }
+    }
+}
+
+pub const BPS_BASE: u16 = 10_000;
+
+// 与前端 pets.js 的 id 对应；activity_bps 顺序为
+// [FriendAccepted, MessageSent, ExpenseRecorded, SplitPaid]
+pub const PET_SPECIES: [PetSpecies; 10] = [
+    // 1. Dragon：所有活动 +15%
+    PetSpecies::new("Dragon", [11_500; REWARD_ACTION_COUNT], 11_500, 11_500),
+    // 2. Cat：消费记录 +20%
+    PetSpecies::new(
+        "Cat",
+        [BPS_BASE, BPS_BASE, 12_000, BPS_BASE],
+        BPS_BASE,
+        BPS_BASE,
+    ),
+    // 3. Dog：好友互动 +20%
+    PetSpecies::new(
+        "Dog",
+        [12_000, BPS_BASE, BPS_BASE, BPS_BASE],
+        BPS_BASE,
+        BPS_BASE,
+    ),
+    // 4. Pig：链上没有可靠随机数，"10% 概率双倍" 折算为所有活动 +10%
+    PetSpecies::new("Pig", [11_000; REWARD_ACTION_COUNT], 11_000, 11_000),
+    // 5. Monkey：完成任务 +40%
+    PetSpecies::new("Monkey", [BPS_BASE; REWARD_ACTION_COUNT], BPS_BASE, 14_000),
+    // 6. Cow：每日照顾 +20%
+    PetSpecies::new("Cow", [BPS_BASE; REWARD_ACTION_COUNT], 12_000, BPS_BASE),
+    // 7. Rabbit：所有活动 +10%
+    PetSpecies::new("Rabbit", [11_000; REWARD_ACTION_COUNT], 11_000, 11_000),
+    // 8. Tiger：转账分账 +30%
+    PetSpecies::new(
+        "Tiger",
+        [BPS_BASE, BPS_BASE, BPS_BASE, 13_000],
+        BPS_BASE,
+        BPS_BASE,
+    ),
+    // 9. Goat：所有分类消费 +10%
+    PetSpecies::new(
+        "Goat",
+        [BPS_BASE, BPS_BASE, 11_000, BPS_BASE],
+        BPS_BASE,
+        BPS_BASE,
+    ),
+    // 10. Mouse：发送消息 +50%
+    PetSpecies::new(
+        "Mouse",
+        [BPS_BASE, 15_000, BPS_BASE, BPS_BASE],
+        BPS_BASE,
+        BPS_BASE,
+    ),
+];
+
+const UNKNOWN_SPECIES: PetSpecies = PetSpecies::new(
+    "Unknown",
+    [BPS_BASE; REWARD_ACTION_COUNT],
+    BPS_BASE,
+    BPS_BASE,
+);
+
+pub fn pet_species(pet_id: u8) -> &'static PetSpecies {
+    match pet_id {
+        1..=10 => &PET_SPECIES[pet_id as usize - 1],
+        _ => &UNKNOWN_SPECIES,
+    }
+}
+
+pub fn apply_bps(amount: u32, bps: u16) -> u32 {
+    (amount as u64 * bps as u64 / BPS_BASE as u64).min(u32::MAX as u64) as u32
+}
+
+pub const PET_NFT_SYMBOL: &str = "SMPET";
+pub const PET_NFT_MAX_URI_LEN: usize = 200;
+
+/// NFT 名称由链上状态生成，例如 "Dragon Lv.7 Adult"
+pub fn pet_nft_data(pet: &PetState, uri: String) -> DataV2 {
+    DataV2 {
+        name: format!(
+            "{} Lv.{} {:?}",
+            pet_species(pet.pet_id).name,
+            pet.level,
+            pet.stage
+        ),
+        symbol: PET_NFT_SYMBOL.to_string(),
+        uri,
+        seller_fee_basis_points: 0,
+        creators: None,
+        collection: None,
+        uses: None,
+    }
+}
+
+pub const SECONDS_PER_HOUR: i64 = 60 * 60;
+pub const SECONDS_PER_DAY: i64 = 24 * SECONDS_PER_HOUR;
+
+pub const PET_STAT_MAX: u8 = 100;
+pub const PET_MAX_LEVEL: u8 = 10;
+// 每一级所需的累计经验值 (Level 1-10)
+pub const PET_LEVEL_XP: [u32; PET_MAX_LEVEL as usize] =
+    [0, 100, 250, 500, 1000, 2000, 3500, 5500, 8000, 12000];
+
+pub const PET_FEED_COOLDOWN: i64 = 6 * SECONDS_PER_HOUR;
+pub const PET_FEED_HAPPINESS: u8 = 10;
+pub const PET_FEED_ENERGY: u8 = 20;
+pub const PET_FEED_XP: u32 = 5;
+
+pub const PET_PLAY_COOLDOWN: i64 = 4 * SECONDS_PER_HOUR;
+pub const PET_PLAY_HAPPINESS: u8 = 10;
+pub const PET_PLAY_ENERGY_COST: u8 = 20;
+pub const PET_PLAY_XP: u32 = 10;
+
+pub const PET_HUNGER_GRACE: i64 = 12 * SECONDS_PER_HOUR;
+pub const PET_HUNGER_DECAY_PER_HOUR: i64 = 2;
+pub const PET_ENERGY_REGEN_PER_HOUR: i64 = 5;
+
+pub fn level_for_xp(xp: u32) -> u8 {
+    PET_LEVEL_XP
+        .iter()
+        .filter(|&&required| xp >= required)
+        .count() as u8
+}
+
+fn clamp_stat(value: i64) -> u8 {
+    value.clamp(0, PET_STAT_MAX as i64) as u8
+}
+
+// 新增：每日任务进度账户
+#[account]
+#[derive(InitSpace)]
+pub struct QuestProgress {
+    pub owner: Pubkey,
+    pub day: i64, // UTC 日序号 (unix_timestamp / 86400)
+    pub progress: [u16; QUEST_COUNT],
+    pub claimed: [bool; QUEST_COUNT],
+    pub streak: u32,
+    pub best_streak: u32,
+    pub last_completed_day: i64,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; ACCOUNT_RESERVED_BYTES],
+}
+
+impl QuestProgress {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+
+    /// 跨日时清空当日进度；如果错过了一整天，连续天数归零
+    pub fn roll_over(&mut self, now: i64) {
+        let day = now.div_euclid(SECONDS_PER_DAY);
+        if day == self.day {
+            return;
+        }
+
+        self.day = day;
+        self.progress = [0; QUEST_COUNT];
+        self.claimed = [false; QUEST_COUNT];
+        if self.last_completed_day < day - 1 {
+            self.streak = 0;
+        }
+    }
+}
+
+// 新增：每日任务种类，顺序即进度数组下标
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum QuestKind {
+    SendMessages,
+    RecordExpenses,
+    AddFriend,
+}
+
+pub const QUEST_COUNT: usize = 3;
+
+pub struct QuestDefinition {
+    pub target: u16,
+    pub reward_xp: u32,
+}
+
+impl QuestKind {
+    pub fn definition(self) -> QuestDefinition {
+        match self {
+            QuestKind::SendMessages => QuestDefinition {
+                target: 5,
+                reward_xp: 30,
+            },
+            QuestKind::RecordExpenses => QuestDefinition {
+                target: 3,
+                reward_xp: 50,
+            },
+            QuestKind::AddFriend => QuestDefinition {
+                target: 1,
+                reward_xp: 40,
+            },
+        }
+    }
+}
+
+/// 如果调用者附带了任务进度账户，则累加对应任务的进度
+pub fn track_quest_progress(
+    quest_progress: &mut Option<Account<QuestProgress>>,
+    quest: QuestKind,
+    now: i64,
+) {
+    if let Some(quests) = quest_progress.as_mut() {
+        quests.roll_over(now);
+        let slot = quest as usize;
+        quests.progress[slot] = quests.progress[slot].saturating_add(1);
+    }
+}
+
+// 新增：好友圈，PDA = ["circle", owner, created_at]
+#[account]
+#[derive(InitSpace)]
+pub struct Circle {
+    pub owner: Pubkey,
+    #[max_len(CIRCLE_NAME_MAX_LEN)]
+    pub name: String,
+    #[max_len(CIRCLE_MAX_MEMBERS)]
+    pub members: Vec<MemberEntry>, // 包含 Owner 本人
+    #[max_len(CIRCLE_MAX_INVITES)]
+    pub invites: Vec<Pubkey>,
+    pub created_at: i64,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; ACCOUNT_RESERVED_BYTES],
+}
+
+impl Circle {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+
+    pub fn role_of(&self, user: &Pubkey) -> Option<MemberRole> {
+        member_role(&self.members, user)
+    }
+
+    pub fn is_admin(&self, user: &Pubkey) -> bool {
+        matches!(self.role_of(user), Some(role) if role.is_admin())
+    }
+
+    /// 只有 Owner / Admin 可以邀请，且对方还不是成员也未被邀请
+    pub fn check_invite(&self, user: &Pubkey, invitee: &Pubkey) -> Result<()> {
+        require!(self.is_admin(user), ErrorCode::NotCircleAdmin);
+        require!(
+            self.role_of(invitee).is_none(),
+            ErrorCode::AlreadyCircleMember
+        );
+        require!(!self.invites.contains(invitee), ErrorCode::AlreadyInvited);
+        require!(
+            self.invites.len() < CIRCLE_MAX_INVITES,
+            ErrorCode::CircleInviteListFull
+        );
+        Ok(())
+    }
+
+    /// 必须被邀请且圈子未满，返回邀请在列表中的位置
+    pub fn check_join(&self, user: &Pubkey) -> Result<usize> {
+        let position = self
+            .invites
+            .iter()
+            .position(|invitee| invitee == user)
+            .ok_or(ErrorCode::NotInvitedToCircle)?;
+        require!(
+            self.members.len() < CIRCLE_MAX_MEMBERS,
+            ErrorCode::CircleFull
+        );
+        Ok(position)
+    }
+
+    pub fn check_leave(&self, user: &Pubkey) -> Result<()> {
+        let role = self.role_of(user).ok_or(ErrorCode::NotCircleMember)?;
+        require!(role != MemberRole::Owner, ErrorCode::CircleOwnerCannotLeave);
+        Ok(())
+    }
+
+    pub fn check_remove(&self, user: &Pubkey, member: &Pubkey) -> Result<()> {
+        let user_role = self.role_of(user).ok_or(ErrorCode::NotCircleMember)?;
+        let member_role = self.role_of(member).ok_or(ErrorCode::NotCircleMember)?;
+        require!(user_role.outranks(member_role), ErrorCode::NotCircleAdmin);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod circle_tests {
+    use super::*;
+
+    struct Roles {
+        owner: Pubkey,
+        admin: Pubkey,
+        member: Pubkey,
+        outsider: Pubkey,
+    }
+
+    fn roles() -> Roles {
+        Roles {
+            owner: Pubkey::new_unique(),
+            admin: Pubkey::new_unique(),
+            member: Pubkey::new_unique(),
+            outsider: Pubkey::new_unique(),
+        }
+    }
+
+    fn members(roles: &Roles) -> Vec<MemberEntry> {
+        vec![
+            MemberEntry {
+                member: roles.owner,
+                role: MemberRole::Owner,
+            },
+            MemberEntry {
+                member: roles.admin,
+                role: MemberRole::Admin,
+            },
+            MemberEntry {
+                member: roles.member,
+                role: MemberRole::Member,
+            },
+        ]
+    }
+
+    fn circle(roles: &Roles) -> Circle {
+        Circle {
+            owner: roles.owner,
+            name: "circle".to_string(),
+            members: members(roles),
+            invites: Vec::new(),
+            created_at: 0,
+            bump: 255,
+            version: ACCOUNT_VERSION,
+            reserved: [0; ACCOUNT_RESERVED_BYTES],
+        }
+    }
+
+    #[test]
+    fn outsider_cannot_invite_to_circle() {
+        let roles = roles();
+        let circle = circle(&roles);
+        let invitee = Pubkey::new_unique();
+
+        assert_eq!(
+            circle.check_invite(&roles.outsider, &invitee).unwrap_err(),
+            ErrorCode::NotCircleAdmin.into()
+        );
+        assert_eq!(
+            circle.check_invite(&roles.member, &invitee).unwrap_err(),
+            ErrorCode::NotCircleAdmin.into()
+        );
+        assert!(circle.check_invite(&roles.owner, &invitee).is_ok());
+        assert!(circle.check_invite(&roles.admin, &invitee).is_ok());
+    }
+
+    #[test]
+    fn circle_invite_rejects_members_duplicates_and_full_list() {
+        let roles = roles();
+        let mut circle = circle(&roles);
+        let invitee = Pubkey::new_unique();
+
+        assert_eq!(
+            circle
+                .check_invite(&roles.owner, &roles.member)
+                .unwrap_err(),
+            ErrorCode::AlreadyCircleMember.into()
+        );
+
+        circle.invites.push(invitee);
+        assert_eq!(
+            circle.check_invite(&roles.owner, &invitee).unwrap_err(),
+            ErrorCode::AlreadyInvited.into()
+        );
+
+        circle
+            .invites
+            .resize(CIRCLE_MAX_INVITES, Pubkey::new_unique());
+        assert_eq!(
+            circle
+                .check_invite(&roles.owner, &Pubkey::new_unique())
+                .unwrap_err(),
+            ErrorCode::CircleInviteListFull.into()
+        );
+    }
+
+    #[test]
+    fn outsider_cannot_join_uninvited_or_full_circle() {
+        let roles = roles();
+        let mut circle = circle(&roles);
+
+        assert_eq!(
+            circle.check_join(&roles.outsider).unwrap_err(),
+            ErrorCode::NotInvitedToCircle.into()
+        );
+
+        circle.invites.push(roles.outsider);
+        assert_eq!(circle.check_join(&roles.outsider).unwrap(), 0);
+
+        while circle.members.len() < CIRCLE_MAX_MEMBERS {
+            circle.members.push(MemberEntry {
+                member: Pubkey::new_unique(),
+                role: MemberRole::Member,
+            });
+        }
+        assert_eq!(
+            circle.check_join(&roles.outsider).unwrap_err(),
+            ErrorCode::CircleFull.into()
+        );
+    }
+
+    #[test]
+    fn outsider_cannot_leave_or_remove_circle_members() {
+        let roles = roles();
+        let circle = circle(&roles);
+
+        assert_eq!(
+            circle.check_leave(&roles.outsider).unwrap_err(),
+            ErrorCode::NotCircleMember.into()
+        );
+        assert_eq!(
+            circle.check_leave(&roles.owner).unwrap_err(),
+            ErrorCode::CircleOwnerCannotLeave.into()
+        );
+        assert!(circle.check_leave(&roles.member).is_ok());
+
+        assert_eq!(
+            circle
+                .check_remove(&roles.outsider, &roles.member)
+                .unwrap_err(),
+            ErrorCode::NotCircleMember.into()
+        );
+        assert_eq!(
+            circle
+                .check_remove(&roles.owner, &roles.outsider)
+                .unwrap_err(),
+            ErrorCode::NotCircleMember.into()
+        );
+        assert_eq!(
+            circle
+                .check_remove(&roles.member, &roles.admin)
+                .unwrap_err(),
+            ErrorCode::NotCircleAdmin.into()
+        );
+        assert_eq!(
+            circle.check_remove(&roles.admin, &roles.owner).unwrap_err(),
+            ErrorCode::NotCircleAdmin.into()
+        );
+        assert!(circle.check_remove(&roles.admin, &roles.member).is_ok());
+        assert!(circle.check_remove(&roles.owner, &roles.admin).is_ok());
+    }
+}
+
+pub const CIRCLE_NAME_MAX_LEN: usize = 32;
+pub const CIRCLE_MAX_MEMBERS: usize = 20;
+pub const CIRCLE_MAX_INVITES: usize = 10;
+
+// 新增：GroupChatRoom，PDA = ["group_chat", owner, created_at]
+#[account]
+#[derive(InitSpace)]
+pub struct GroupChatRoom {
+    pub owner: Pubkey,
+    #[max_len(GROUP_CHAT_NAME_MAX_LEN)]
+    pub name: String,
+    pub topic: Pubkey, // 关联的分账、福利活动或好友圈，Pubkey::default() 表示无
+    #[max_len(GROUP_CHAT_MAX_MEMBERS)]
+    pub members: Vec<MemberEntry>, // 包含 Owner 本人
+    #[max_len(GROUP_CHAT_MAX_INVITES)]
+    pub invites: Vec<Pubkey>,
+    pub message_count: u64,
+    pub last_message_at: i64,
+    pub created_at: i64,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; ACCOUNT_RESERVED_BYTES],
+}
+
+impl GroupChatRoom {
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+
+    pub fn role_of(&self, user: &Pubkey) -> Option<MemberRole> {
+        member_role(&self.members, user)
+    }
+
+    pub fn is_admin(&self, user: &Pubkey) -> bool {
+        matches!(self.role_of(user), Some(role) if role.is_admin())
+    }
+
+    pub fn check_member(&self, user: &Pubkey) -> Result<MemberRole> {
+        Ok(self.role_of(user).ok_or(ErrorCode::NotGroupChatMember)?)
+    }
+
+    /// 只有 Owner / Admin 可以邀请，且对方还不是成员也未被邀请
+    pub fn check_invite(&self, user: &Pubkey, invitee: &Pubkey) -> Result<()> {
+        require!(self.is_admin(user), ErrorCode::NotGroupChatAdmin);
+        require!(
+            self.role_of(invitee).is_none(),
+            ErrorCode::AlreadyGroupChatMember
+        );
+        require!(!self.invites.contains(invitee), ErrorCode::AlreadyInvited);
+        require!(
+            self.invites.len() < GROUP_CHAT_MAX_INVITES,
+            ErrorCode::GroupChatInviteListFull
+        );
+        Ok(())
+    }
+
+    /// 必须被邀请且群聊未满，返回邀请在列表中的位置
+    pub fn check_join(&self, user: &Pubkey) -> Result<usize> {
+        let position = self
+            .invites
+            .iter()
+            .position(|invitee| invitee == user)
+            .ok_or(ErrorCode::NotInvitedToGroupChat)?;
+        require!(
+            self.members.len() < GROUP_CHAT_MAX_MEMBERS,
+            ErrorCode::GroupChatFull
+        );
+        Ok(position)
+    }
+
+    pub fn check_leave(&self, user: &Pubkey) -> Result<()> {
+        let role = self.check_member(user)?;
+        require!(
+            role != MemberRole::Owner,
+            ErrorCode::GroupChatOwnerCannotLeave
+        );
+        Ok(())
+    }
+
+    pub fn check_remove(&self, user: &Pubkey, member: &Pubkey) -> Result<()> {
+        let user_role = self.check_member(user)?;
+        let member_role = self.check_member(member)?;
+        require!(
+            user_role.outranks(member_role),
+            ErrorCode::NotGroupChatAdmin
+        );
+        Ok(())
+    }
+
+    /// 消息所在的聊天室是群聊时，发送者必须仍是成员才能编辑或删除；一对一聊天室不受影响
+    pub fn check_live_member(chat_room: &AccountInfo, user: &Pubkey) -> Result<()> {
+        let data = chat_room.try_borrow_data()?;
+        if chat_room.owner != &crate::ID
+            || data.len() < 8
+            || data[..8] != <GroupChatRoom as anchor_lang::Discriminator>::discriminator()
+        {
+            return Ok(());
+        }
+        GroupChatRoom::try_deserialize(&mut &data[..])?.check_member(user)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod group_message_tests {
+    use super::*;
+
+    fn check(owner: &Pubkey, account: &impl AccountSerialize, user: &Pubkey) -> Result<()> {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        let info = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            owner,
+            false,
+            0,
+        );
+        GroupChatRoom::check_live_member(&info, user)
+    }
+
+    #[test]
+    fn group_messages_require_live_membership() {
+        let owner = Pubkey::new_unique();
+        let member = Pubkey::new_unique();
+        let removed = Pubkey::new_unique();
+        let room = GroupChatRoom {
+            owner,
+            name: "group".to_string(),
+            topic: Pubkey::default(),
+            members: vec![
+                MemberEntry {
+                    member: owner,
+                    role: MemberRole::Owner,
+                },
+                MemberEntry {
+                    member,
+                    role: MemberRole::Member,
+                },
+            ],
+            invites: Vec::new(),
+            message_count: 2,
+            last_message_at: 0,
+            created_at: 0,
+            bump: 255,
+            version: ACCOUNT_VERSION,
+            reserved: [0; ACCOUNT_RESERVED_BYTES],
+        };
+
+        assert!(check(&crate::ID, &room, &member).is_ok());
+        assert!(check(&crate::ID, &room, &owner).is_ok());
+        assert_eq!(
+            check(&crate::ID, &room, &removed).unwrap_err(),
+            ErrorCode::NotGroupChatMember.into()
+        );
+        // 不属于本程序的账户不当作群聊
+        assert!(check(&Pubkey::new_unique(), &room, &removed).is_ok());
+    }
+
+    #[test]
+    fn direct_chat_messages_are_not_affected() {
+        let chat_room = ChatRoom {
+            user_a: Pubkey::new_unique(),
+            user_b: Pubkey::new_unique(),
+            message_count: 0,
+            last_message_at: 0,
+            bump: 255,
+            version: ACCOUNT_VERSION,
+            page_count: 0,
+            oldest_page: 0,
+            retained_pages: 0,
+            compressed: false,
+            tree_root: [0; 32],
+            tree_leaf_count: 0,
+            reserved: [0; ACCOUNT_RESERVED_BYTES - 51],
+        };
+        assert!(check(&crate::ID, &chat_room, &chat_room.user_a).is_ok());
+    }
+
+    #[test]
+    fn outsider_cannot_post_to_group_chat() {
+        let owner = Pubkey::new_unique();
+        let member = Pubkey::new_unique();
+        let outsider = Pubkey::new_unique();
+        let room = GroupChatRoom {
+            owner,
+            name: "group".to_string(),
+            topic: Pubkey::default(),
+            members: vec![
+                MemberEntry {
+                    member: owner,
+                    role: MemberRole::Owner,
+                },
+                MemberEntry {
+                    member,
+                    role: MemberRole::Member,
+                },
+            ],
+            invites: vec![outsider],
+            message_count: 0,
+            last_message_at: 0,
+            created_at: 0,
+            bump: 255,
+            version: ACCOUNT_VERSION,
+            reserved: [0; ACCOUNT_RESERVED_BYTES],
+        };
+
+        // 只被邀请、还没加入也不能发言
+        assert_eq!(
+            room.check_member(&outsider).unwrap_err(),
+            ErrorCode::NotGroupChatMember.into()
+        );
+        assert_eq!(room.check_member(&member).unwrap(), MemberRole::Member);
+        assert!(!room.is_admin(&outsider));
+    }
+
+    fn group(owner: Pubkey, admin: Pubkey, member: Pubkey) -> GroupChatRoom {
+        GroupChatRoom {
+            owner,
+            name: "group".to_string(),
+            topic: Pubkey::default(),
+            members: vec![
+                MemberEntry {
+                    member: owner,
+                    role: MemberRole::Owner,
+                },
+                MemberEntry {
+                    member: admin,
+                    role: MemberRole::Admin,
+                },
+                MemberEntry {
+                    member,
+                    role: MemberRole::Member,
+                },
+            ],
+            invites: Vec::new(),
+            message_count: 0,
+            last_message_at: 0,
+            created_at: 0,
+            bump: 255,
+            version: ACCOUNT_VERSION,
+            reserved: [0; ACCOUNT_RESERVED_BYTES],
+        }
+    }
+
+    #[test]
+    fn group_invite_and_join_checks() {
+        let (owner, admin, member) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let mut room = group(owner, admin, member);
+        let invitee = Pubkey::new_unique();
+
+        assert_eq!(
+            room.check_invite(&member, &invitee).unwrap_err(),
+            ErrorCode::NotGroupChatAdmin.into()
+        );
+        assert_eq!(
+            room.check_invite(&Pubkey::new_unique(), &invitee)
+                .unwrap_err(),
+            ErrorCode::NotGroupChatAdmin.into()
+        );
+        assert_eq!(
+            room.check_invite(&owner, &member).unwrap_err(),
+            ErrorCode::AlreadyGroupChatMember.into()
+        );
+        assert!(room.check_invite(&admin, &invitee).is_ok());
+
+        assert_eq!(
+            room.check_join(&invitee).unwrap_err(),
+            ErrorCode::NotInvitedToGroupChat.into()
+        );
+        room.invites.push(invitee);
+        assert_eq!(
+            room.check_invite(&owner, &invitee).unwrap_err(),
+            ErrorCode::AlreadyInvited.into()
+        );
+        assert_eq!(room.check_join(&invitee).unwrap(), 0);
+
+        room.invites
+            .resize(GROUP_CHAT_MAX_INVITES, Pubkey::new_unique());
+        assert_eq!(
+            room.check_invite(&owner, &Pubkey::new_unique())
+                .unwrap_err(),
+            ErrorCode::GroupChatInviteListFull.into()
+        );
+
+        while room.members.len() < GROUP_CHAT_MAX_MEMBERS {
+            room.members.push(MemberEntry {
+                member: Pubkey::new_unique(),
+                role: MemberRole::Member,
+            });
+        }
+        assert_eq!(
+            room.check_join(&invitee).unwrap_err(),
+            ErrorCode::GroupChatFull.into()
+        );
+    }
+
+    #[test]
+    fn group_leave_and_remove_checks() {
+        let (owner, admin, member) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let outsider = Pubkey::new_unique();
+        let room = group(owner, admin, member);
+
+        assert_eq!(
+            room.check_leave(&outsider).unwrap_err(),
+            ErrorCode::NotGroupChatMember.into()
+        );
+        assert_eq!(
+            room.check_leave(&owner).unwrap_err(),
+            ErrorCode::GroupChatOwnerCannotLeave.into()
+        );
+        assert!(room.check_leave(&member).is_ok());
+
+        assert_eq!(
+            room.check_remove(&outsider, &member).unwrap_err(),
+            ErrorCode::NotGroupChatMember.into()
+        );
+        assert_eq!(
+            room.check_remove(&owner, &outsider).unwrap_err(),
+            ErrorCode::NotGroupChatMember.into()
+        );
+        assert_eq!(
+            room.check_remove(&member, &admin).unwrap_err(),
+            ErrorCode::NotGroupChatAdmin.into()
+        );
+        assert_eq!(
+            room.check_remove(&admin, &owner).unwrap_err(),
+            ErrorCode::NotGroupChatAdmin.into()
+        );
+        assert!(room.check_remove(&admin, &member).is_ok());
+        assert!(room.check_remove(&owner, &admin).is_ok());
+    }
+}
+
+pub const GROUP_CHAT_NAME_MAX_LEN: usize = 32;
+pub const GROUP_CHAT_MAX_MEMBERS: usize = 32;
+pub const GROUP_CHAT_MAX_INVITES: usize = 16;
+
+// 新增：好友圈和群聊共用的成员列表项
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct MemberEntry {
+    pub member: Pubkey,
+    pub role: MemberRole,
+}
+
+pub fn member_role(members: &[MemberEntry], user: &Pubkey) -> Option<MemberRole> {
+    members
+        .iter()
+        .find(|entry| entry.member == *user)
+        .map(|entry| entry.role)
+}
+
+// 新增：好友圈和群聊的成员角色
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum MemberRole {
+    Owner,
+    Admin,
+    Member,
+}
+
+impl MemberRole {
+    pub fn is_admin(self) -> bool {
+        matches!(self, MemberRole::Owner | MemberRole::Admin)
+    }
+
+    /// 是否有权移除对方：Owner 可以移除 Admin 和 Member，Admin 只能移除 Member
+    pub fn outranks(self, other: MemberRole) -> bool {
+        matches!(
+            (self, other),
+            (MemberRole::Owner, MemberRole::Admin)
+                | (MemberRole::Owner, MemberRole::Member)
+                | (MemberRole::Admin, MemberRole::Member)
+        )
+    }
+}
+
+#[cfg(test)]
+mod account_space_tests {
+    use super::*;
+
+    /// 最坏情况（所有 String/Vec 取最大长度）序列化后必须正好等于 LEN，并能反序列化回来
+    fn assert_worst_case_fits<T: AccountSerialize + AccountDeserialize>(account: &T, len: usize) {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), len);
+        T::try_deserialize(&mut data.as_slice()).unwrap();
+    }
+
+    fn max_string(len: usize) -> String {
+        "a".repeat(len)
+    }
+
+    fn max_members(len: usize) -> Vec<MemberEntry> {
+        (0..len)
+            .map(|_| MemberEntry {
+                member: Pubkey::new_unique(),
+                role: MemberRole::Member,
+            })
+            .collect()
+    }
+
+    fn max_keys(len: usize) -> Vec<Pubkey> {
+        (0..len).map(|_| Pubkey::new_unique()).collect()
+    }
+
+    #[test]
+    fn profile_accounts_fit() {
+        assert_worst_case_fits(
+            &UserProfile {
+                owner: Pubkey::new_unique(),
+                username: "汉".repeat(USERNAME_MAX_BYTES / 3),
+                avatar: max_string(AVATAR_MAX_LEN),
+                pet_id: 10,
+                friend_count: u32::MAX,
+                created_at: i64::MAX,
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                pending_request_count: u32::MAX,
+                reserved: [0; ACCOUNT_RESERVED_BYTES - 4],
+            },
+            UserProfile::LEN,
+        );
+        assert_worst_case_fits(
+            &UsernameRecord {
+                owner: Pubkey::new_unique(),
+                username: max_string(USERNAME_MAX_BYTES),
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                reserved: [0; ACCOUNT_RESERVED_BYTES],
+            },
+            UsernameRecord::LEN,
+        );
+        assert_worst_case_fits(
+            &ProfileDetails {
+                owner: Pubkey::new_unique(),
+                bio: max_string(PROFILE_BIO_MAX_BYTES),
+                image_uri: max_string(PROFILE_URI_MAX_LEN),
+                links: vec![max_string(PROFILE_LINK_MAX_LEN); PROFILE_MAX_LINKS],
+                updated_at: i64::MAX,
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                reserved: [0; ACCOUNT_RESERVED_BYTES],
+            },
+            ProfileDetails::LEN,
+        );
+    }
+
+    #[test]
+    fn social_accounts_fit() {
+        assert_worst_case_fits(
+            &Friendship {
+                user_a: Pubkey::new_unique(),
+                user_b: Pubkey::new_unique(),
+                requester: Pubkey::new_unique(),
+                status: FriendshipStatus::Accepted,
+                created_at: i64::MAX,
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                indexed: true,
+                user_a_page: u32::MAX,
+                user_b_page: u32::MAX,
+                reserved: [0; ACCOUNT_RESERVED_BYTES - 9],
+            },
+            Friendship::LEN,
+        );
+        assert_worst_case_fits(
+            &FriendIndex {
+                owner: Pubkey::new_unique(),
+                friend_count: u32::MAX,
+                page_count: u32::MAX,
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                reserved: [0; ACCOUNT_RESERVED_BYTES],
+            },
+            FriendIndex::LEN,
+        );
+        assert_worst_case_fits(
+            &FriendPage {
+                owner: Pubkey::new_unique(),
+                page: u32::MAX,
+                friends: max_keys(FRIEND_PAGE_SIZE),
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                reserved: [0; ACCOUNT_RESERVED_BYTES],
+            },
+            FriendPage::LEN,
+        );
+        assert_worst_case_fits(
+            &UserBlock {
+                blocker: Pubkey::new_unique(),
+                blocked: Pubkey::new_unique(),
+                created_at: i64::MAX,
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                reserved: [0; ACCOUNT_RESERVED_BYTES],
+            },
+            UserBlock::LEN,
+        );
+        assert_worst_case_fits(
+            &Circle {
+                owner: Pubkey::new_unique(),
+                name: max_string(CIRCLE_NAME_MAX_LEN),
+                members: max_members(CIRCLE_MAX_MEMBERS),
+                invites: max_keys(CIRCLE_MAX_INVITES),
+                created_at: i64::MAX,
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                reserved: [0; ACCOUNT_RESERVED_BYTES],
+            },
+            Circle::LEN,
+        );
+    }
+
+    #[test]
+    fn chat_accounts_fit() {
+        assert_worst_case_fits(
+            &ChatRoom {
+                user_a: Pubkey::new_unique(),
+                user_b: Pubkey::new_unique(),
+                message_count: u64::MAX,
+                last_message_at: i64::MAX,
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                page_count: u32::MAX,
+                oldest_page: u32::MAX,
+                retained_pages: u16::MAX,
+                compressed: true,
+                tree_root: [255; 32],
+                tree_leaf_count: u64::MAX,
+                reserved: [0; ACCOUNT_RESERVED_BYTES - 51],
+            },
+            ChatRoom::LEN,
+        );
+        assert_worst_case_fits(
+            &EncryptionKey {
+                owner: Pubkey::new_unique(),
+                public_key: [255; 32],
+                key_version: u32::MAX,
+                updated_at: i64::MAX,
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                reserved: [0; ACCOUNT_RESERVED_BYTES],
+            },
+            EncryptionKey::LEN,
+        );
+        assert_worst_case_fits(
+            &ChatConfig {
+                authority: Pubkey::new_unique(),
+                require_friendship: true,
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                edit_window_secs: i64::MAX,
+                reserved: [0; ACCOUNT_RESERVED_BYTES - 8],
+            },
+            ChatConfig::LEN,
+        );
+        assert_worst_case_fits(
+            &Message {
+                chat_room: Pubkey::new_unique(),
+                sender: Pubkey::new_unique(),
+                content: vec![255; MESSAGE_CIPHERTEXT_MAX_LEN],
+                message_index: u64::MAX,
+                timestamp: i64::MAX,
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                encrypted: true,
+                nonce: [255; ENCRYPTION_NONCE_LEN],
+                sender_key_version: u32::MAX,
+                recipient_key_version: u32::MAX,
+                edited_at: i64::MAX,
+                edit_count: u16::MAX,
+                reserved: [0; ACCOUNT_RESERVED_BYTES - 43],
+            },
+            Message::LEN,
+        );
+        assert_worst_case_fits(
+            &ChatPage {
+                chat_room: Pubkey::new_unique(),
+                page_index: u32::MAX,
+                payer: Pubkey::new_unique(),
+                entry_count: u16::MAX,
+                last_message_at: i64::MAX,
+                data: vec![255; CHAT_PAGE_DATA_LEN],
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                reserved: [0; ACCOUNT_RESERVED_BYTES],
+            },
+            ChatPage::LEN,
+        );
+        assert_worst_case_fits(
+            &GroupChatRoom {
+                owner: Pubkey::new_unique(),
+                name: max_string(GROUP_CHAT_NAME_MAX_LEN),
+                topic: Pubkey::new_unique(),
+                members: max_members(GROUP_CHAT_MAX_MEMBERS),
+                invites: max_keys(GROUP_CHAT_MAX_INVITES),
+                message_count: u64::MAX,
+                last_message_at: i64::MAX,
+                created_at: i64::MAX,
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                reserved: [0; ACCOUNT_RESERVED_BYTES],
+            },
+            GroupChatRoom::LEN,
+        );
+    }
+
+    #[test]
+    fn expense_and_funding_accounts_fit() {
+        assert_worst_case_fits(
+            &ExpenseRecord {
+                owner: Pubkey::new_unique(),
+                recipient: Pubkey::new_unique(),
+                amount: u64::MAX,
+                category: ExpenseCategory::Dining,
+                description: max_string(DESCRIPTION_MAX_LEN),
+                timestamp: i64::MAX,
+                tx_signature: max_string(TX_SIGNATURE_MAX_LEN),
+                record_index: u64::MAX,
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                reserved: [0; ACCOUNT_RESERVED_BYTES],
+            },
+            ExpenseRecord::LEN,
+        );
+        assert_worst_case_fits(
+            &ExpenseStats {
+                owner: Pubkey::new_unique(),
+                total_spent: u64::MAX,
+                record_count: u64::MAX,
+                dining_total: u64::MAX,
+                shopping_total: u64::MAX,
+                entertainment_total: u64::MAX,
+                travel_total: u64::MAX,
+                gifts_total: u64::MAX,
+                bills_total: u64::MAX,
+                other_total: u64::MAX,
+                last_updated: i64::MAX,
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                reserved: [0; ACCOUNT_RESERVED_BYTES],
+            },
+            ExpenseStats::LEN,
+        );
+        assert_worst_case_fits(
+            &FundingEvent {
+                creator: Pubkey::new_unique(),
+                title: max_string(TITLE_MAX_LEN),
+                total_amount: u64::MAX,
+                remaining_amount: u64::MAX,
+                deadline: i64::MAX,
+                ipfs_hash: max_string(IPFS_HASH_MAX_LEN),
+                status: EventStatus::Closed,
+                created_at: i64::MAX,
+                application_count: u32::MAX,
+                approved_count: u32::MAX,
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                reserved: [0; ACCOUNT_RESERVED_BYTES],
+            },
+            FundingEvent::LEN,
+        );
+        assert_worst_case_fits(
+            &Application {
+                event: Pubkey::new_unique(),
+                applicant: Pubkey::new_unique(),
+                requested_amount: u64::MAX,
+                approved_amount: u64::MAX,
+                ipfs_hash: max_string(IPFS_HASH_MAX_LEN),
+                status: ApplicationStatus::Approved,
+                applied_at: i64::MAX,
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                reserved: [0; ACCOUNT_RESERVED_BYTES],
+            },
+            Application::LEN,
+        );
+        assert_worst_case_fits(
+            &GroupSplit {
+                creator: Pubkey::new_unique(),
+                title: max_string(TITLE_MAX_LEN),
+                total_amount: u64::MAX,
+                member_count: u8::MAX,
+                amount_per_person: u64::MAX,
+                ipfs_hash: max_string(IPFS_HASH_MAX_LEN),
+                created_at: i64::MAX,
+                settled_count: u32::MAX,
+                status: SplitStatus::Settled,
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                reserved: [0; ACCOUNT_RESERVED_BYTES],
+            },
+            GroupSplit::LEN,
+        );
+        assert_worst_case_fits(
+            &SplitMember {
+                split: Pubkey::new_unique(),
+                member: Pubkey::new_unique(),
+                amount_owed: u64::MAX,
+                paid: true,
+                paid_at: i64::MAX,
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                reserved: [0; ACCOUNT_RESERVED_BYTES],
+            },
+            SplitMember::LEN,
+        );
+    }
+
+    #[test]
+    fn pet_and_quest_accounts_fit() {
+        assert_worst_case_fits(
+            &PetState {
+                owner: Pubkey::new_unique(),
+                pet_id: 10,
+                level: u8::MAX,
+                xp: u32::MAX,
+                happiness: 100,
+                energy: 100,
+                last_fed: i64::MAX,
+                last_played: i64::MAX,
+                last_decay_at: i64::MAX,
+                total_interactions: u32::MAX,
+                created_at: i64::MAX,
+                reward_day: i64::MAX,
+                daily_xp: [u32::MAX; REWARD_ACTION_COUNT],
+                stage: PetStage::Adult,
+                evolved_at: i64::MAX,
+                nft_mint: Pubkey::new_unique(),
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                reserved: [0; ACCOUNT_RESERVED_BYTES],
+            },
+            PetState::LEN,
+        );
+        assert_worst_case_fits(
+            &RewardConfig {
+                authority: Pubkey::new_unique(),
+                xp_rewards: [u32::MAX; REWARD_ACTION_COUNT],
+                daily_caps: [u32::MAX; REWARD_ACTION_COUNT],
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                reserved: [0; ACCOUNT_RESERVED_BYTES],
+            },
+            RewardConfig::LEN,
+        );
+        assert_worst_case_fits(
+            &QuestProgress {
+                owner: Pubkey::new_unique(),
+                day: i64::MAX,
+                progress: [u16::MAX; QUEST_COUNT],
+                claimed: [true; QUEST_COUNT],
+                streak: u32::MAX,
+                best_streak: u32::MAX,
+                last_completed_day: i64::MAX,
+                bump: 255,
+                version: ACCOUNT_VERSION,
+                reserved: [0; ACCOUNT_RESERVED_BYTES],
+            },
+            QuestProgress::LEN,
+        );
+    }
+
+    #[test]
+    fn fixed_layouts_only_grow_by_the_reserve() {
+        // v0 字段 + version + 预留区；新增字段必须从 reserved 中划出，不能改变账户大小
+        assert_eq!(
+            ChatRoom::LEN,
+            8 + 32 + 32 + 8 + 8 + 1 + 1 + ACCOUNT_RESERVED_BYTES
+        );
+        assert_eq!(
+            Friendship::LEN,
+            8 + 32 + 32 + 32 + 1 + 8 + 1 + 1 + ACCOUNT_RESERVED_BYTES
+        );
+    }
+}
+
+// ============================================================================
+// COMPRESSION - 压缩聊天记录 (SPL Account Compression)
+// ============================================================================
+
+/// 手动构造 SPL Account Compression / Noop 的 CPI，
+/// 避免引入依赖旧版 anchor-lang 的 spl-account-compression crate
+pub mod compression {
+    use super::*;
+    use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+    use anchor_lang::solana_program::keccak;
+    use anchor_lang::solana_program::program::{invoke, invoke_signed};
+
+    pub mod account_compression {
+        use super::*;
+        declare_id!("cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK");
+    }
+
+    pub mod noop {
+        use super::*;
+        declare_id!("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+    }
+
+    pub const ACCOUNT_COMPRESSION_ID: Pubkey = account_compression::ID;
+    pub const NOOP_ID: Pubkey = noop::ID;
+
+    /// 2^20 条消息；depth 20 支持的最小 changelog buffer 是 64
+    pub const CHAT_TREE_MAX_DEPTH: usize = 20;
+    pub const CHAT_TREE_MAX_BUFFER_SIZE: usize = 64;
+
+    // ConcurrentMerkleTreeHeader (56) + sequence_number/active_index/buffer_size (24)
+    // + changelogs + rightmost_proof，不带 canopy
+    const TREE_HEADER_LEN: usize = 56;
+    const TREE_AUTHORITY_OFFSET: usize = 10;
+    const TREE_CHANGE_LOG_LEN: usize = 32 + 32 * CHAT_TREE_MAX_DEPTH + 8;
+    const TREE_RIGHTMOST_PROOF_LEN: usize = 32 * CHAT_TREE_MAX_DEPTH + 40;
+    pub const CHAT_TREE_ACCOUNT_LEN: usize = TREE_HEADER_LEN
+        + 24
+        + CHAT_TREE_MAX_BUFFER_SIZE * TREE_CHANGE_LOG_LEN
+        + TREE_RIGHTMOST_PROOF_LEN;
+
+    // sha256("global:<ix>")[..8]
+    const INIT_EMPTY_MERKLE_TREE_DISCRIMINATOR: [u8; 8] = [191, 11, 119, 7, 180, 107, 220, 110];
+    const APPEND_DISCRIMINATOR: [u8; 8] = [149, 120, 18, 222, 236, 225, 88, 203];
+
+    /// 写入 noop 日志的压缩消息；叶子 = keccak(chat_room, sender, message_index, timestamp, content)
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct CompressedChatMessage {
+        pub chat_room: Pubkey,
+        pub sender: Pubkey,
+        pub message_index: u64,
+        pub leaf_index: u64,
+        pub timestamp: i64,
+        pub content: Vec<u8>,
+    }
+
+    impl CompressedChatMessage {
+        pub fn leaf_hash(&self) -> [u8; 32] {
+            keccak::hashv(&[
+                self.chat_room.as_ref(),
+                self.sender.as_ref(),
+                &self.message_index.to_le_bytes(),
+                &self.timestamp.to_le_bytes(),
+                &self.content,
+            ])
+            .to_bytes()
+        }
+    }
+
+    pub fn init_empty_merkle_tree<'info>(
+        compression_program: &AccountInfo<'info>,
+        merkle_tree: &AccountInfo<'info>,
+        authority: &AccountInfo<'info>,
+        noop: &AccountInfo<'info>,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        let mut data = INIT_EMPTY_MERKLE_TREE_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&(CHAT_TREE_MAX_DEPTH as u32).to_le_bytes());
+        data.extend_from_slice(&(CHAT_TREE_MAX_BUFFER_SIZE as u32).to_le_bytes());
+
+        invoke_signed(
+            &Instruction {
+                program_id: ACCOUNT_COMPRESSION_ID,
+                accounts: vec![
+                    AccountMeta::new(merkle_tree.key(), false),
+                    AccountMeta::new_readonly(authority.key(), true),
+                    AccountMeta::new_readonly(noop.key(), false),
+                ],
+                data,
+            },
+            &[
+                merkle_tree.clone(),
+                authority.clone(),
+                noop.clone(),
+                compression_program.clone(),
+            ],
+            signer_seeds,
+        )?;
+        Ok(())
+    }
+
+    pub fn append<'info>(
+        compression_program: &AccountInfo<'info>,
+        merkle_tree: &AccountInfo<'info>,
+        authority: &AccountInfo<'info>,
+        noop: &AccountInfo<'info>,
+        leaf: [u8; 32],
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        let mut data = APPEND_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&leaf);
+
+        invoke_signed(
+            &Instruction {
+                program_id: ACCOUNT_COMPRESSION_ID,
+                accounts: vec![
+                    AccountMeta::new(merkle_tree.key(), false),
+                    AccountMeta::new_readonly(authority.key(), true),
+                    AccountMeta::new_readonly(noop.key(), false),
+                ],
+                data,
+            },
+            &[
+                merkle_tree.clone(),
+                authority.clone(),
+                noop.clone(),
+                compression_program.clone(),
+            ],
+            signer_seeds,
+        )?;
+        Ok(())
+    }
+
+    /// 把数据作为 noop 指令的 data 写进交易，索引器从内部指令中读取
+    pub fn log_to_noop<'info>(noop: &AccountInfo<'info>, data: Vec<u8>) -> Result<()> {
+        invoke(
+            &Instruction {
+                program_id: NOOP_ID,
+                accounts: vec![],
+                data,
+            },
+            std::slice::from_ref(noop),
+        )?;
+        Ok(())
+    }
+
+    /// 树 header 中记录的 authority
+    pub fn tree_authority(data: &[u8]) -> Option<Pubkey> {
+        data.get(TREE_AUTHORITY_OFFSET..TREE_AUTHORITY_OFFSET + 32)
+            .map(|bytes| Pubkey::try_from(bytes).unwrap())
+    }
+
+    /// 当前 root = change_logs[active_index].root
+    pub fn tree_root(data: &[u8]) -> Result<[u8; 32]> {
+        let tree = data
+            .get(TREE_HEADER_LEN..)
+            .ok_or(ErrorCode::InvalidChatTree)?;
+        let active_index = tree
+            .get(8..16)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()) as usize)
+            .ok_or(ErrorCode::InvalidChatTree)?;
+        let offset = 24 + active_index * TREE_CHANGE_LOG_LEN;
+
+        tree.get(offset..offset + 32)
+            .map(|bytes| bytes.try_into().unwrap())
+            .ok_or(ErrorCode::InvalidChatTree.into())
+    }
+}
+
+// ============================================================================
+// MIGRATION - 账户迁移
+// ============================================================================
+
+/// 可迁移的账户：旧布局是新布局去掉 version/reserved 后的前缀
+pub trait Migratable:
+    AccountSerialize + AccountDeserialize + anchor_lang::Discriminator + Owner
+{
+    type Legacy: AnchorDeserialize;
+    const SPACE: usize;
+
+    fn version(&self) -> u8;
+    fn from_legacy(legacy: Self::Legacy) -> Self;
+}
+
+/// 把 v0 账户 realloc 到当前布局：payer 补足租金差额，新字段填默认值
+pub fn migrate_account<'info, T: Migratable>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+) -> Result<()> {
+    require!(
+        account.owner == &crate::ID,
+        ErrorCode::InvalidMigrationAccount
+    );
+
+    let legacy = {
+        let data = account.try_borrow_data()?;
+        require!(
+            data.len() >= 8 && data[..8] == T::discriminator(),
+            ErrorCode::InvalidMigrationAccount
+        );
+        // 旧账户末尾是 0，按新布局读出来 version 也是 0
+        if let Ok(current) = T::try_deserialize(&mut &data[..]) {
+            require!(
+                current.version() < ACCOUNT_VERSION,
+                ErrorCode::AccountAlreadyMigrated
+            );
+        }
+        T::Legacy::deserialize(&mut &data[8..])
+            .map_err(|_| error!(ErrorCode::InvalidMigrationAccount))?
+    };
+
+    if account.data_len() < T::SPACE {
+        let required = Rent::get()?.minimum_balance(T::SPACE);
+        let shortfall = required.saturating_sub(account.lamports());
+        if shortfall > 0 {
+            let transfer_ix = anchor_lang::solana_program::system_instruction::transfer(
+                payer.key,
+                account.key,
+                shortfall,
+            );
+            anchor_lang::solana_program::program::invoke(
+                &transfer_ix,
+                &[payer.clone(), account.clone()],
+            )?;
+        }
+        account.realloc(T::SPACE, false)?;
+    }
+
+    let migrated = T::from_legacy(legacy);
+    let mut data = account.try_borrow_mut_data()?;
+    let mut writer: &mut [u8] = &mut data;
+    migrated.try_serialize(&mut writer)?;
+    Ok(())
+}
+
+/// 没有 version 字段的 v0 账户布局
+pub mod legacy {
+    use super::*;
+
+    #[derive(AnchorDeserialize)]
+    pub struct UserProfileV0 {
+        pub owner: Pubkey,
+        pub username: String,
+        pub avatar: String,
+        pub pet_id: u8,
+        pub friend_count: u32,
+        pub created_at: i64,
+        pub bump: u8,
+    }
+
+    #[derive(AnchorDeserialize)]
+    pub struct FriendshipV0 {
+        pub user_a: Pubkey,
+        pub user_b: Pubkey,
+        pub requester: Pubkey,
+        pub status: FriendshipStatus,
+        pub created_at: i64,
+        pub bump: u8,
+    }
+
+    #[derive(AnchorDeserialize)]
+    pub struct ChatRoomV0 {
+        pub user_a: Pubkey,
+        pub user_b: Pubkey,
+        pub message_count: u64,
+        pub last_message_at: i64,
+        pub bump: u8,
+    }
+
+    #[derive(AnchorDeserialize)]
+    pub struct MessageV0 {
+        pub chat_room: Pubkey,
+        pub sender: Pubkey,
+        pub content: String,
+        pub message_index: u64,
+        pub timestamp: i64,
+        pub bump: u8,
+    }
+
+    #[derive(AnchorDeserialize)]
+    pub struct ExpenseRecordV0 {
+        pub owner: Pubkey,
+        pub recipient: Pubkey,
+        pub amount: u64,
+        pub category: ExpenseCategory,
+        pub description: String,
+        pub timestamp: i64,
+        pub tx_signature: String,
+        pub record_index: u64,
+        pub bump: u8,
+    }
+
+    #[derive(AnchorDeserialize)]
+    pub struct ExpenseStatsV0 {
+        pub owner: Pubkey,
+        pub total_spent: u64,
+        pub record_count: u64,
+        pub dining_total: u64,
+        pub shopping_total: u64,
+        pub entertainment_total: u64,
+        pub travel_total: u64,
+        pub gifts_total: u64,
+        pub bills_total: u64,
+        pub other_total: u64,
+        pub last_updated: i64,
+        pub bump: u8,
+    }
+
+    #[derive(AnchorDeserialize)]
+    pub struct FundingEventV0 {
+        pub creator: Pubkey,
+        pub title: String,
+        pub total_amount: u64,
+        pub remaining_amount: u64,
+        pub deadline: i64,
+        pub ipfs_hash: String,
+        pub status: EventStatus,
+        pub created_at: i64,
+        pub application_count: u32,
+        pub approved_count: u32,
+        pub bump: u8,
+    }
+
+    #[derive(AnchorDeserialize)]
+    pub struct ApplicationV0 {
+        pub event: Pubkey,
+        pub applicant: Pubkey,
+        pub requested_amount: u64,
+        pub approved_amount: u64,
+        pub ipfs_hash: String,
+        pub status: ApplicationStatus,
+        pub applied_at: i64,
+        pub bump: u8,
+    }
+
+    #[derive(AnchorDeserialize)]
+    pub struct GroupSplitV0 {
+        pub creator: Pubkey,
+        pub title: String,
+        pub total_amount: u64,
+        pub member_count: u8,
+        pub amount_per_person: u64,
+        pub ipfs_hash: String,
+        pub created_at: i64,
+        pub settled_count: u32,
+        pub status: SplitStatus,
+        pub bump: u8,
+    }
+
+    #[derive(AnchorDeserialize)]
+    pub struct SplitMemberV0 {
+        pub split: Pubkey,
+        pub member: Pubkey,
+        pub amount_owed: u64,
+        pub paid: bool,
+        pub paid_at: i64,
+        pub bump: u8,
+    }
+}
+
+impl Migratable for UserProfile {
+    type Legacy = legacy::UserProfileV0;
+    const SPACE: usize = UserProfile::LEN;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn from_legacy(legacy: Self::Legacy) -> Self {
+        Self {
+            owner: legacy.owner,
+            username: legacy.username,
+            avatar: legacy.avatar,
+            pet_id: legacy.pet_id,
+            friend_count: legacy.friend_count,
+            created_at: legacy.created_at,
+            bump: legacy.bump,
+            version: ACCOUNT_VERSION,
+            pending_request_count: 0,
+            reserved: [0; ACCOUNT_RESERVED_BYTES - 4],
+        }
+    }
+}
+
+impl Migratable for Friendship {
+    type Legacy = legacy::FriendshipV0;
+    const SPACE: usize = Friendship::LEN;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn from_legacy(legacy: Self::Legacy) -> Self {
+        Self {
+            user_a: legacy.user_a,
+            user_b: legacy.user_b,
+            requester: legacy.requester,
+            status: legacy.status,
+            created_at: legacy.created_at,
+            bump: legacy.bump,
+            version: ACCOUNT_VERSION,
+            indexed: false,
+            user_a_page: 0,
+            user_b_page: 0,
+            reserved: [0; ACCOUNT_RESERVED_BYTES - 9],
+        }
+    }
+}
+
+impl Migratable for ChatRoom {
+    type Legacy = legacy::ChatRoomV0;
+    const SPACE: usize = ChatRoom::LEN;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn from_legacy(legacy: Self::Legacy) -> Self {
+        Self {
+            user_a: legacy.user_a,
+            user_b: legacy.user_b,
+            message_count: legacy.message_count,
+            last_message_at: legacy.last_message_at,
+            bump: legacy.bump,
+            version: ACCOUNT_VERSION,
+            page_count: 0,
+            oldest_page: 0,
+            retained_pages: 0,
+            compressed: false,
+            tree_root: [0; 32],
+            tree_leaf_count: 0,
+            reserved: [0; ACCOUNT_RESERVED_BYTES - 51],
+        }
+    }
+}
+
+impl Migratable for Message {
+    type Legacy = legacy::MessageV0;
+    const SPACE: usize = Message::LEN;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn from_legacy(legacy: Self::Legacy) -> Self {
+        Self {
+            chat_room: legacy.chat_room,
+            sender: legacy.sender,
+            content: legacy.content.into_bytes(),
+            message_index: legacy.message_index,
+            timestamp: legacy.timestamp,
+            bump: legacy.bump,
+            version: ACCOUNT_VERSION,
+            encrypted: false,
+            nonce: [0; ENCRYPTION_NONCE_LEN],
+            sender_key_version: 0,
+            recipient_key_version: 0,
+            edited_at: 0,
+            edit_count: 0,
+            reserved: [0; ACCOUNT_RESERVED_BYTES - 43],
+        }
+    }
+}
+
+impl Migratable for ExpenseRecord {
+    type Legacy = legacy::ExpenseRecordV0;
+    const SPACE: usize = ExpenseRecord::LEN;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn from_legacy(legacy: Self::Legacy) -> Self {
+        Self {
+            owner: legacy.owner,
+            recipient: legacy.recipient,
+            amount: legacy.amount,
+            category: legacy.category,
+            description: legacy.description,
+            timestamp: legacy.timestamp,
+            tx_signature: legacy.tx_signature,
+            record_index: legacy.record_index,
+            bump: legacy.bump,
+            version: ACCOUNT_VERSION,
+            reserved: [0; ACCOUNT_RESERVED_BYTES],
+        }
+    }
+}
+
+impl Migratable for ExpenseStats {
+    type Legacy = legacy::ExpenseStatsV0;
+    const SPACE: usize = ExpenseStats::LEN;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn from_legacy(legacy: Self::Legacy) -> Self {
+        Self {
+            owner: legacy.owner,
+            total_spent: legacy.total_spent,
+            record_count: legacy.record_count,
+            dining_total: legacy.dining_total,
+            shopping_total: legacy.shopping_total,
+            entertainment_total: legacy.entertainment_total,
+            travel_total: legacy.travel_total,
+            gifts_total: legacy.gifts_total,
+            bills_total: legacy.bills_total,
+            other_total: legacy.other_total,
+            last_updated: legacy.last_updated,
+            bump: legacy.bump,
+            version: ACCOUNT_VERSION,
+            reserved: [0; ACCOUNT_RESERVED_BYTES],
+        }
+    }
+}
+
+impl Migratable for FundingEvent {
+    type Legacy = legacy::FundingEventV0;
+    const SPACE: usize = FundingEvent::LEN;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn from_legacy(legacy: Self::Legacy) -> Self {
+        Self {
+            creator: legacy.creator,
+            title: legacy.title,
+            total_amount: legacy.total_amount,
+            remaining_amount: legacy.remaining_amount,
+            deadline: legacy.deadline,
+            ipfs_hash: legacy.ipfs_hash,
+            status: legacy.status,
+            created_at: legacy.created_at,
+            application_count: legacy.application_count,
+            approved_count: legacy.approved_count,
+            bump: legacy.bump,
+            version: ACCOUNT_VERSION,
+            reserved: [0; ACCOUNT_RESERVED_BYTES],
+        }
+    }
+}
+
+impl Migratable for Application {
+    type Legacy = legacy::ApplicationV0;
+    const SPACE: usize = Application::LEN;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn from_legacy(legacy: Self::Legacy) -> Self {
+        Self {
+            event: legacy.event,
+            applicant: legacy.applicant,
+            requested_amount: legacy.requested_amount,
+            approved_amount: legacy.approved_amount,
+            ipfs_hash: legacy.ipfs_hash,
+            status: legacy.status,
+            applied_at: legacy.applied_at,
+            bump: legacy.bump,
+            version: ACCOUNT_VERSION,
+            reserved: [0; ACCOUNT_RESERVED_BYTES],
+        }
+    }
+}
+
+impl Migratable for GroupSplit {
+    type Legacy = legacy::GroupSplitV0;
+    const SPACE: usize = GroupSplit::LEN;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn from_legacy(legacy: Self::Legacy) -> Self {
+        Self {
+            creator: legacy.creator,
+            title: legacy.title,
+            total_amount: legacy.total_amount,
+            member_count: legacy.member_count,
+            amount_per_person: legacy.amount_per_person,
+            ipfs_hash: legacy.ipfs_hash,
+            created_at: legacy.created_at,
+            settled_count: legacy.settled_count,
+            status: legacy.status,
+            bump: legacy.bump,
+            version: ACCOUNT_VERSION,
+            reserved: [0; ACCOUNT_RESERVED_BYTES],
+        }
+    }
+}
+
+impl Migratable for SplitMember {
+    type Legacy = legacy::SplitMemberV0;
+    const SPACE: usize = SplitMember::LEN;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn from_legacy(legacy: Self::Legacy) -> Self {
+        Self {
+            split: legacy.split,
+            member: legacy.member,
+            amount_owed: legacy.amount_owed,
+            paid: legacy.paid,
+            paid_at: legacy.paid_at,
+            bump: legacy.bump,
+            version: ACCOUNT_VERSION,
+            reserved: [0; ACCOUNT_RESERVED_BYTES],
+        }
+    }
+}
+
+// ============================================================================
+// CONTEXT STRUCTURES - 上下文结构
+// ============================================================================
+
+// --- Social Program Contexts ---
+
+#[derive(Accounts)]
+#[instruction(username: String, avatar: String)]
+pub struct InitializeProfile<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = UserProfile::LEN,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = UsernameRecord::LEN,
+        seeds = [b"username", validation::username_seed(&username).as_ref()],
+        bump
+    )]
+    pub username_record: Account<'info, UsernameRecord>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProfile<'info> {
+    // 旧 Profile 按 20 bytes 用户名分配，更新时扩容到当前大小
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump = user_profile.bump,
+        constraint = user_profile.owner == user.key() @ ErrorCode::Unauthorized,
+        realloc = UserProfile::LEN,
+        realloc::payer = user,
+        realloc::zero = false
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(new_username: String)]
+pub struct ChangeUsername<'info> {
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump = user_profile.bump,
+        constraint = user_profile.owner == user.key() @ ErrorCode::Unauthorized,
+        realloc = UserProfile::LEN,
+        realloc::payer = user,
+        realloc::zero = false
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    /// CHECK: Registry entry of the current username; may not exist for legacy profiles
+    #[account(
+        mut,
+        seeds = [b"username", validation::username_seed(&user_profile.username).as_ref()],
+        bump
+    )]
+    pub old_username_record: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = UsernameRecord::LEN,
+        seeds = [b"username", validation::username_seed(&new_username).as_ref()],
+        bump
+    )]
+    pub new_username_record: Account<'info, UsernameRecord>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProfileDetails<'info> {
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = ProfileDetails::LEN,
+        seeds = [b"profile_details", user.key().as_ref()],
+        bump
+    )]
+    pub profile_details: Account<'info, ProfileDetails>,
+
+    #[account(
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump = user_profile.bump,
+        constraint = user_profile.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseProfile<'info> {
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump = user_profile.bump,
+        constraint = user_profile.owner == user.key() @ ErrorCode::Unauthorized,
+        constraint = user_profile.friend_count == 0 @ ErrorCode::ProfileHasFriends,
+        constraint = user_profile.pending_request_count == 0
+            @ ErrorCode::ProfileHasPendingRequests
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    /// CHECK: Registry entry of the current username; may not exist for legacy profiles
+    #[account(
+        mut,
+        seeds = [b"username", validation::username_seed(&user_profile.username).as_ref()],
+        bump
+    )]
+    pub username_record: UncheckedAccount<'info>,
+
+    /// CHECK: Must be empty; expense stats are closed separately via close_expense_stats
+    #[account(seeds = [b"expense_stats", user.key().as_ref()], bump)]
+    pub expense_stats: UncheckedAccount<'info>,
+
+    /// CHECK: User's pet, closed together with the profile if present
+    #[account(mut, seeds = [b"pet", user.key().as_ref()], bump)]
+    pub pet_state: UncheckedAccount<'info>,
+
+    /// CHECK: User's quest progress, closed together with the profile if present
+    #[account(mut, seeds = [b"quest_progress", user.key().as_ref()], bump)]
+    pub quest_progress: UncheckedAccount<'info>,
+
+    /// CHECK: User's extended profile details, closed together with the profile if present
+    #[account(mut, seeds = [b"profile_details", user.key().as_ref()], bump)]
+    pub profile_details: UncheckedAccount<'info>,
+
+    /// CHECK: User's published encryption key, closed together with the profile if present
+    #[account(mut, seeds = [b"encryption_key", user.key().as_ref()], bump)]
+    pub encryption_key: UncheckedAccount<'info>,
+
+    /// CHECK: User's friend index, closed together with its pages if present
+    #[account(mut, seeds = [b"friend_index", user.key().as_ref()], bump)]
+    pub friend_index: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SelectPet<'info> {
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump = user_profile.bump,
+        has_one = owner @ ErrorCode::Unauthorized
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: This is the owner field in user_profile
+    pub owner: AccountInfo<'info>,
+
+    /// CHECK: Pet PDA of the user; must not exist, the pet has to be released first
+    #[account(seeds = [b"pet", user.key().as_ref()], bump)]
+    pub pet_state: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(user_a: Pubkey, user_b: Pubkey)]
+pub struct SendFriendRequest<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = Friendship::LEN,
+        seeds = [b"friendship", user_a.as_ref(), user_b.as_ref()],
+        bump
+    )]
+    pub friendship: Account<'info, Friendship>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: Friend's public key
+    pub friend: AccountInfo<'info>,
+
+    /// CHECK: Block PDA created by the sender against the friend; must not exist
+    #[account(seeds = [b"block", user.key().as_ref(), friend.key().as_ref()], bump)]
+    pub sender_block: UncheckedAccount<'info>,
+
+    /// CHECK: Block PDA created by the friend against the sender; must not exist
+    #[account(seeds = [b"block", friend.key().as_ref(), user.key().as_ref()], bump)]
+    pub friend_block: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump = sender_profile.bump
+    )]
+    pub sender_profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", friend.key().as_ref()],
+        bump = friend_profile.bump
+    )]
+    pub friend_profile: Account<'info, UserProfile>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(user_a_page: u32, user_b_page: u32)]
+pub struct AcceptFriendRequest<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"friendship",
+            friendship.user_a.as_ref(),
+            friendship.user_b.as_ref()
+        ],
+        bump = friendship.bump,
+        // 只有被请求方可以接受，请求方和第三方都不行
+        constraint = friendship.recipient() == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub friendship: Account<'info, Friendship>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", friendship.user_a.as_ref()],
+        bump = user_a_profile.bump
+    )]
+    pub user_a_profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", friendship.user_b.as_ref()],
+        bump = user_b_profile.bump
+    )]
+    pub user_b_profile: Account<'info, UserProfile>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = FriendIndex::LEN,
+        seeds = [b"friend_index", friendship.user_a.as_ref()],
+        bump
+    )]
+    pub user_a_friend_index: Account<'info, FriendIndex>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = FriendPage::LEN,
+        seeds = [b"friend_page", friendship.user_a.as_ref(), &user_a_page.to_le_bytes()],
+        bump
+    )]
+    pub user_a_friend_page: Account<'info, FriendPage>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = FriendIndex::LEN,
+        seeds = [b"friend_index", friendship.user_b.as_ref()],
+        bump
+    )]
+    pub user_b_friend_index: Account<'info, FriendIndex>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = FriendPage::LEN,
+        seeds = [b"friend_page", friendship.user_b.as_ref(), &user_b_page.to_le_bytes()],
+        bump
+    )]
+    pub user_b_friend_page: Account<'info, FriendPage>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        mut,
+        seeds = [b"pet", user.key().as_ref()],
+        bump = pet_state.bump,
+        constraint = pet_state.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub pet_state: Option<Account<'info, PetState>>,
+
+    #[account(seeds = [b"reward_config"], bump = reward_config.bump)]
+    pub reward_config: Option<Account<'info, RewardConfig>>,
+
+    #[account(
+        mut,
+        seeds = [b"quest_progress", user.key().as_ref()],
+        bump = quest_progress.bump
+    )]
+    pub quest_progress: Option<Account<'info, QuestProgress>>,
+
+    #[account(
+        mut,
+        seeds = [b"quest_progress", friendship.requester.as_ref()],
+        bump = requester_quest_progress.bump
+    )]
+    pub requester_quest_progress: Option<Account<'info, QuestProgress>>,
+
+    #[account(
+        mut,
+        seeds = [b"pet", friendship.requester.as_ref()],
+        bump = requester_pet_state.bump,
+        constraint = requester_pet_state.owner == friendship.requester @ ErrorCode::Unauthorized
+    )]
+    pub requester_pet_state: Option<Account<'info, PetState>>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveFriend<'info> {
+    #[account(
+        mut,
+        close = requester,
+        seeds = [
+            b"friendship",
+            friendship.user_a.as_ref(),
+            friendship.user_b.as_ref()
+        ],
+        bump = friendship.bump,
+        constraint = friendship.is_member(&user.key()) @ ErrorCode::Unauthorized
+    )]
+    pub friendship: Account<'info, Friendship>,
+
+    /// CHECK: Original requester, who paid for the friendship and gets the rent back
+    #[account(mut, address = friendship.requester @ ErrorCode::Unauthorized)]
+    pub requester: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", friendship.user_a.as_ref()],
+        bump = user_a_profile.bump
+    )]
+    pub user_a_profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", friendship.user_b.as_ref()],
+        bump = user_b_profile.bump
+    )]
+    pub user_b_profile: Account<'info, UserProfile>,
+
+    pub user: Signer<'info>,
+
+    // 已写入好友分页的好友关系必须传入双方的 index 和 page
+    #[account(mut)]
+    pub user_a_friend_index: Option<Account<'info, FriendIndex>>,
+
+    #[account(mut)]
+    pub user_a_friend_page: Option<Account<'info, FriendPage>>,
+
+    #[account(mut)]
+    pub user_b_friend_index: Option<Account<'info, FriendIndex>>,
+
+    #[account(mut)]
+    pub user_b_friend_page: Option<Account<'info, FriendPage>>,
+}
+
+#[derive(Accounts)]
+pub struct RejectFriendRequest<'info> {
+    #[account(
+        mut,
+        close = requester,
+        seeds = [
+            b"friendship",
+            friendship.user_a.as_ref(),
+            friendship.user_b.as_ref()
+        ],
+        bump = friendship.bump
+    )]
+    pub friendship: Account<'info, Friendship>,
+
+    /// CHECK: Original requester, receives the rent refund
+    #[account(mut, address = friendship.requester @ ErrorCode::Unauthorized)]
+    pub requester: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", friendship.user_a.as_ref()],
+        bump = user_a_profile.bump
+    )]
+    pub user_a_profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", friendship.user_b.as_ref()],
+        bump = user_b_profile.bump
+    )]
+    pub user_b_profile: Account<'info, UserProfile>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelFriendRequest<'info> {
+    #[account(
+        mut,
+        close = user,
+        seeds = [
+            b"friendship",
+            friendship.user_a.as_ref(),
+            friendship.user_b.as_ref()
+        ],
+        bump = friendship.bump
+    )]
+    pub friendship: Account<'info, Friendship>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", friendship.user_a.as_ref()],
+        bump = user_a_profile.bump
+    )]
+    pub user_a_profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", friendship.user_b.as_ref()],
+        bump = user_b_profile.bump
+    )]
+    pub user_b_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct BlockUser<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = UserBlock::LEN,
+        seeds = [b"block", user.key().as_ref(), blocked.key().as_ref()],
+        bump
+    )]
+    pub user_block: Account<'info, UserBlock>,
+
+    /// CHECK: User being blocked
+    pub blocked: AccountInfo<'info>,
+
+    /// CHECK: Friendship PDA between the two users; closed if it exists
+    #[account(
+        mut,
+        seeds = [
+            b"friendship",
+            user.key().min(blocked.key()).as_ref(),
+            user.key().max(blocked.key()).as_ref()
+        ],
+        bump
+    )]
+    pub friendship: UncheckedAccount<'info>,
+
+    /// CHECK: Friendship requester receiving the rent refund; required if the friendship exists
+    #[account(mut)]
+    pub requester: Option<UncheckedAccount<'info>>,
+
+    // 好友关系或请求存在时必须传入双方的 Profile
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump = blocker_profile.bump
+    )]
+    pub blocker_profile: Option<Account<'info, UserProfile>>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", blocked.key().as_ref()],
+        bump = blocked_profile.bump
+    )]
+    pub blocked_profile: Option<Account<'info, UserProfile>>,
+
+    #[account(mut)]
+    pub blocker_friend_index: Option<Account<'info, FriendIndex>>,
+
+    #[account(mut)]
+    pub blocker_friend_page: Option<Account<'info, FriendPage>>,
+
+    #[account(mut)]
+    pub blocked_friend_index: Option<Account<'info, FriendIndex>>,
+
+    #[account(mut)]
+    pub blocked_friend_page: Option<Account<'info, FriendPage>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnblockUser<'info> {
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"block", user.key().as_ref(), user_block.blocked.as_ref()],
+        bump = user_block.bump,
+        constraint = user_block.blocker == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub user_block: Account<'info, UserBlock>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+// --- Chat Program Contexts ---
+
+#[derive(Accounts)]
+pub struct InitializeChatRoom<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = ChatRoom::LEN,
+        seeds = [b"chat_room", user_a.key().as_ref(), user_b.key().as_ref()],
+        bump
+    )]
+    pub chat_room: Account<'info, ChatRoom>,
+
+    /// CHECK: User A's public key
+    pub user_a: AccountInfo<'info>,
+
+    /// CHECK: User B's public key
+    pub user_b: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Global chat config; friendship is only enforced once it exists
+    #[account(seeds = [b"chat_config"], bump)]
+    pub chat_config: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"friendship", user_a.key().as_ref(), user_b.key().as_ref()],
+        bump = friendship.bump
+    )]
+    pub friendship: Option<Account<'info, Friendship>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SendMessage<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"chat_room",
+            chat_room.user_a.as_ref(),
+            chat_room.user_b.as_ref()
+        ],
+        bump = chat_room.bump
+    )]
+    pub chat_room: Account<'info, ChatRoom>,
+
+    #[account(
+        init,
+        payer = sender,
+        space = Message::LEN,
+        seeds = [
+            b"message",
+            chat_room.key().as_ref(),
+            chat_room.message_count.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub message: Account<'info, Message>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    /// CHECK: Block PDA created by the sender against the other member; must not exist
+    #[account(
+        seeds = [
+            b"block",
+            sender.key().as_ref(),
+            chat_room.other_member(&sender.key()).as_ref()
+        ],
+        bump
+    )]
+    pub sender_block: UncheckedAccount<'info>,
+
+    /// CHECK: Block PDA created by the other member against the sender; must not exist
+    #[account(
+        seeds = [
+            b"block",
+            chat_room.other_member(&sender.key()).as_ref(),
+            sender.key().as_ref()
+        ],
+        bump
+    )]
+    pub peer_block: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        mut,
+        seeds = [b"pet", sender.key().as_ref()],
+        bump = pet_state.bump,
+        constraint = pet_state.owner == sender.key() @ ErrorCode::Unauthorized
+    )]
+    pub pet_state: Option<Account<'info, PetState>>,
+
+    #[account(seeds = [b"reward_config"], bump = reward_config.bump)]
+    pub reward_config: Option<Account<'info, RewardConfig>>,
+
+    #[account(
+        mut,
+        seeds = [b"quest_progress", sender.key().as_ref()],
+        bump = quest_progress.bump
+    )]
+    pub quest_progress: Option<Account<'info, QuestProgress>>,
+}
+
+#[derive(Accounts)]
+pub struct SendEncryptedMessage<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"chat_room",
+            chat_room.user_a.as_ref(),
+            chat_room.user_b.as_ref()
+        ],
+        bump = chat_room.bump
+    )]
+    pub chat_room: Account<'info, ChatRoom>,
+
+    #[account(
+        init,
+        payer = sender,
+        space = Message::LEN,
+        seeds = [
+            b"message",
+            chat_room.key().as_ref(),
+            chat_room.message_count.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub message: Account<'info, Message>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    /// CHECK: Block PDA created by the sender against the other member; must not exist
+    #[account(
+        seeds = [
+            b"block",
+            sender.key().as_ref(),
+            chat_room.other_member(&sender.key()).as_ref()
+        ],
+        bump
+    )]
+    pub sender_block: UncheckedAccount<'info>,
+
+    /// CHECK: Block PDA created by the other member against the sender; must not exist
+    #[account(
+        seeds = [
+            b"block",
+            chat_room.other_member(&sender.key()).as_ref(),
+            sender.key().as_ref()
+        ],
+        bump
+    )]
+    pub peer_block: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"encryption_key", sender.key().as_ref()],
+        bump = sender_key.bump
+    )]
+    pub sender_key: Account<'info, EncryptionKey>,
+
+    #[account(
+        seeds = [
+            b"encryption_key",
+            chat_room.other_member(&sender.key()).as_ref()
+        ],
+        bump = recipient_key.bump
+    )]
+    pub recipient_key: Account<'info, EncryptionKey>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        mut,
+        seeds = [b"pet", sender.key().as_ref()],
+        bump = pet_state.bump,
+        constraint = pet_state.owner == sender.key() @ ErrorCode::Unauthorized
+    )]
+    pub pet_state: Option<Account<'info, PetState>>,
+
+    #[account(seeds = [b"reward_config"], bump = reward_config.bump)]
+    pub reward_config: Option<Account<'info, RewardConfig>>,
+
+    #[account(
+        mut,
+        seeds = [b"quest_progress", sender.key().as_ref()],
+        bump = quest_progress.bump
+    )]
+    pub quest_progress: Option<Account<'info, QuestProgress>>,
+}
+
+#[derive(Accounts)]
+pub struct PublishEncryptionKey<'info> {
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = EncryptionKey::LEN,
+        seeds = [b"encryption_key", user.key().as_ref()],
+        bump
+    )]
+    pub encryption_key: Account<'info, EncryptionKey>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DeleteMessage<'info> {
+    #[account(
+        mut,
+        close = sender,
+        seeds = [
+            b"message",
+            message.chat_room.as_ref(),
+            message.message_index.to_le_bytes().as_ref()
+        ],
+        bump = message.bump,
+        has_one = sender @ ErrorCode::Unauthorized
+    )]
+    pub message: Account<'info, Message>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    /// CHECK: Chat room or group chat of the message; group chats require live membership
+    #[account(address = message.chat_room @ ErrorCode::Unauthorized)]
+    pub chat_room: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct EditMessage<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"message",
+            message.chat_room.as_ref(),
+            message.message_index.to_le_bytes().as_ref()
+        ],
+        bump = message.bump,
+        has_one = sender @ ErrorCode::Unauthorized
+    )]
+    pub message: Account<'info, Message>,
+
+    pub sender: Signer<'info>,
+
+    /// CHECK: Global chat config; the default edit window applies until it exists
+    #[account(seeds = [b"chat_config"], bump)]
+    pub chat_config: UncheckedAccount<'info>,
+
+    /// CHECK: Chat room or group chat of the message; group chats require live membership
+    #[account(address = message.chat_room @ ErrorCode::Unauthorized)]
+    pub chat_room: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeChatConfig<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = ChatConfig::LEN,
+        seeds = [b"chat_config"],
+        bump
+    )]
+    pub chat_config: Account<'info, ChatConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        constraint = program.programdata_address()? == Some(program_data.key())
+            @ ErrorCode::Unauthorized
+    )]
+    pub program: Program<'info, crate::program::SolamateProgram>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(authority.key())
+            @ ErrorCode::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateChatConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"chat_config"],
+        bump = chat_config.bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub chat_config: Account<'info, ChatConfig>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(content: String, page_index: u32)]
+pub struct SendPagedMessage<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"chat_room",
+            chat_room.user_a.as_ref(),
+            chat_room.user_b.as_ref()
+        ],
+        bump = chat_room.bump
+    )]
+    pub chat_room: Account<'info, ChatRoom>,
+
+    #[account(
+        init_if_needed,
+        payer = sender,
+        space = ChatPage::LEN,
+        seeds = [
+            b"chat_page",
+            chat_room.key().as_ref(),
+            page_index.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = page_index + 1 == chat_room.page_count
+            || page_index == chat_room.page_count @ ErrorCode::InvalidChatPage
+    )]
+    pub chat_page: Account<'info, ChatPage>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    /// CHECK: Block PDA created by the sender against the other member; must not exist
+    #[account(
+        seeds = [
+            b"block",
+            sender.key().as_ref(),
+            chat_room.other_member(&sender.key()).as_ref()
+        ],
+        bump
+    )]
+    pub sender_block: UncheckedAccount<'info>,
+
+    /// CHECK: Block PDA created by the other member against the sender; must not exist
+    #[account(
+        seeds = [
+            b"block",
+            chat_room.other_member(&sender.key()).as_ref(),
+            sender.key().as_ref()
+        ],
+        bump
+    )]
+    pub peer_block: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        mut,
+        seeds = [b"pet", sender.key().as_ref()],
+        bump = pet_state.bump,
+        constraint = pet_state.owner == sender.key() @ ErrorCode::Unauthorized
+    )]
+    pub pet_state: Option<Account<'info, PetState>>,
+
+    #[account(seeds = [b"reward_config"], bump = reward_config.bump)]
+    pub reward_config: Option<Account<'info, RewardConfig>>,
+
+    #[account(
+        mut,
+        seeds = [b"quest_progress", sender.key().as_ref()],
+        bump = quest_progress.bump
+    )]
+    pub quest_progress: Option<Account<'info, QuestProgress>>,
+}
+
+#[derive(Accounts)]
+pub struct SetChatRetention<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"chat_room",
+            chat_room.user_a.as_ref(),
+            chat_room.user_b.as_ref()
+        ],
+        bump = chat_room.bump
+    )]
+    pub chat_room: Account<'info, ChatRoom>,
+
+    pub user: Signer<'info>,
+
+    // 聊天室的另一位成员，缩短保留页数时必须一起签名
+    pub peer: Option<Signer<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct ReclaimChatPage<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"chat_room",
+            chat_room.user_a.as_ref(),
+            chat_room.user_b.as_ref()
+        ],
+        bump = chat_room.bump
+    )]
+    pub chat_room: Account<'info, ChatRoom>,
+
+    #[account(
+        mut,
+        close = payer,
+        seeds = [
+            b"chat_page",
+            chat_room.key().as_ref(),
+            chat_page.page_index.to_le_bytes().as_ref()
+        ],
+        bump = chat_page.bump,
+        has_one = payer @ ErrorCode::Unauthorized
+    )]
+    pub chat_page: Account<'info, ChatPage>,
+
+    /// CHECK: Original page payer, receives the reclaimed rent
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeChatTree<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"chat_room",
+            chat_room.user_a.as_ref(),
+            chat_room.user_b.as_ref()
+        ],
+        bump = chat_room.bump
+    )]
+    pub chat_room: Account<'info, ChatRoom>,
+
+    /// CHECK: Pre-allocated concurrent Merkle tree owned by the compression program
+    #[account(mut, owner = compression::ACCOUNT_COMPRESSION_ID @ ErrorCode::InvalidChatTree)]
+    pub chat_tree: UncheckedAccount<'info>,
+
+    pub user: Signer<'info>,
+
+    /// CHECK: SPL Account Compression program
+    #[account(address = compression::ACCOUNT_COMPRESSION_ID)]
+    pub compression_program: UncheckedAccount<'info>,
+
+    /// CHECK: SPL Noop program
+    #[account(address = compression::NOOP_ID)]
+    pub noop_program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SendCompressedMessage<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"chat_room",
+            chat_room.user_a.as_ref(),
+            chat_room.user_b.as_ref()
+        ],
+        bump = chat_room.bump
+    )]
+    pub chat_room: Account<'info, ChatRoom>,
+
+    /// CHECK: Must be the Merkle tree whose header authority is this chat room (checked in handler)
+    #[account(
+        mut,
+        owner = compression::ACCOUNT_COMPRESSION_ID @ ErrorCode::InvalidChatTree
+    )]
+    pub chat_tree: UncheckedAccount<'info>,
+
+    pub sender: Signer<'info>,
+
+    /// CHECK: Block PDA created by the sender against the other member; must not exist
+    #[account(
+        seeds = [
+            b"block",
+            sender.key().as_ref(),
+            chat_room.other_member(&sender.key()).as_ref()
+        ],
+        bump
+    )]
+    pub sender_block: UncheckedAccount<'info>,
+
+    /// CHECK: Block PDA created by the other member against the sender; must not exist
+    #[account(
+        seeds = [
+            b"block",
+            chat_room.other_member(&sender.key()).as_ref(),
+            sender.key().as_ref()
+        ],
+        bump
+    )]
+    pub peer_block: UncheckedAccount<'info>,
+
+    /// CHECK: SPL Account Compression program
+    #[account(address = compression::ACCOUNT_COMPRESSION_ID)]
+    pub compression_program: UncheckedAccount<'info>,
+
+    /// CHECK: SPL Noop program
+    #[account(address = compression::NOOP_ID)]
+    pub noop_program: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pet", sender.key().as_ref()],
+        bump = pet_state.bump,
+        constraint = pet_state.owner == sender.key() @ ErrorCode::Unauthorized
+    )]
+    pub pet_state: Option<Account<'info, PetState>>,
+
+    #[account(seeds = [b"reward_config"], bump = reward_config.bump)]
+    pub reward_config: Option<Account<'info, RewardConfig>>,
+
+    #[account(
+        mut,
+        seeds = [b"quest_progress", sender.key().as_ref()],
+        bump = quest_progress.bump
+    )]
+    pub quest_progress: Option<Account<'info, QuestProgress>>,
+}
+
+// --- Expense Program Contexts ---
+
+#[derive(Accounts)]
+pub struct InitializeExpenseStats<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = ExpenseStats::LEN,
+        seeds = [b"expense_stats", user.key().as_ref()],
+        bump
+    )]
+    pub expense_stats: Account<'info, ExpenseStats>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RecordExpense<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = ExpenseRecord::LEN,
+        seeds = [
+            b"expense_record",
+            user.key().as_ref(),
+            expense_stats.record_count.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub expense_record: Account<'info, ExpenseRecord>,
+
+    #[account(
+        mut,
+        seeds = [b"expense_stats", user.key().as_ref()],
+        bump = expense_stats.bump,
+        has_one = owner @ ErrorCode::Unauthorized
+    )]
+    pub expense_stats: Account<'info, ExpenseStats>,
+
+    /// CHECK: This is the owner field in expense_stats
+    pub owner: AccountInfo<'info>,
+
+    /// CHECK: Recipient's public key
+    pub recipient: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        mut,
+        seeds = [b"pet", user.key().as_ref()],
+        bump = pet_state.bump,
+        constraint = pet_state.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub pet_state: Option<Account<'info, PetState>>,
+
+    #[account(seeds = [b"reward_config"], bump = reward_config.bump)]
+    pub reward_config: Option<Account<'info, RewardConfig>>,
+
+    #[account(
+        mut,
+        seeds = [b"quest_progress", user.key().as_ref()],
+        bump = quest_progress.bump
+    )]
+    pub quest_progress: Option<Account<'info, QuestProgress>>,
+}
+
+#[derive(Accounts)]
+pub struct DeleteExpenseRecord<'info> {
+    #[account(
+        mut,
+        close = user,
+        seeds = [
+            b"expense_record",
+            expense_record.owner.as_ref(),
+            expense_record.record_index.to_le_bytes().as_ref()
+        ],
+        bump = expense_record.bump,
+        has_one = owner @ ErrorCode::Unauthorized
+    )]
+    pub expense_record: Account<'info, ExpenseRecord>,
+
+    #[account(
+        mut,
+        seeds = [b"expense_stats", user.key().as_ref()],
+        bump = expense_stats.bump
+    )]
+    pub expense_stats: Account<'info, ExpenseStats>,
+
+    /// CHECK: This is the owner field in expense_record
+    pub owner: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseExpenseStats<'info> {
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"expense_stats", user.key().as_ref()],
+        bump = expense_stats.bump,
+        constraint = expense_stats.owner == user.key() @ ErrorCode::Unauthorized,
+        constraint = expense_stats.record_count == 0 @ ErrorCode::ExpenseRecordsRemaining
+    )]
+    pub expense_stats: Account<'info, ExpenseStats>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+// --- Funding Events Contexts (新增) ---
+
+#[derive(Accounts)]
+#[instruction(title: String, amount: u64, deadline: i64, ipfs_hash: String, timestamp: i64)]
+pub struct CreateFundingEvent<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = FundingEvent::LEN,
+        seeds = [
+            b"funding_event",
+            creator.key().as_ref(),
+            &timestamp.to_le_bytes()
+        ],
+        bump
+    )]
+    pub funding_event: Account<'info, FundingEvent>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApplyForFunding<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"funding_event",
+            funding_event.creator.as_ref(),
+            &funding_event.created_at.to_le_bytes()
+        ],
+        bump = funding_event.bump
+    )]
+    pub funding_event: Account<'info, FundingEvent>,
+
+    #[account(
+        init,
+        payer = applicant,
+        space = Application::LEN,
+        seeds = [
+            b"application",
+            funding_event.key().as_ref(),
+            applicant.key().as_ref()
+        ],
+        bump
+    )]
+    pub application: Account<'info, Application>,
+
+    #[account(mut)]
+    pub applicant: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveApplication<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"funding_event",
+            funding_event.creator.as_ref(),
+            &funding_event.created_at.to_le_bytes()
+        ],
+        bump = funding_event.bump
+    )]
+    pub funding_event: Account<'info, FundingEvent>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"application",
+            funding_event.key().as_ref(),
+            application.applicant.as_ref()
+        ],
+        bump = application.bump
+    )]
+    pub application: Account<'info, Application>,
+
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RejectApplication<'info> {
+    #[account(
+        seeds = [
+            b"funding_event",
+            funding_event.creator.as_ref(),
+            &funding_event.created_at.to_le_bytes()
+        ],
+        bump = funding_event.bump
+    )]
+    pub funding_event: Account<'info, FundingEvent>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"application",
+            funding_event.key().as_ref(),
+            application.applicant.as_ref()
+        ],
+        bump = application.bump
+    )]
+    pub application: Account<'info, Application>,
+
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DisburseFunds<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"funding_event",
+            funding_event.creator.as_ref(),
+            &funding_event.created_at.to_le_bytes()
+        ],
+        bump = funding_event.bump
+    )]
+    pub funding_event: Account<'info, FundingEvent>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"application",
+            funding_event.key().as_ref(),
+            application.applicant.as_ref()
+        ],
+        bump = application.bump
+    )]
+    pub application: Account<'info, Application>,
+
+    /// CHECK: applicant
+    #[account(mut)]
+    pub applicant: AccountInfo<'info>,
+
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseEvent<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"funding_event",
+            funding_event.creator.as_ref(),
+            &funding_event.created_at.to_le_bytes()
+        ],
+        bump = funding_event.bump
+    )]
+    pub funding_event: Account<'info, FundingEvent>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+}
+
+// --- Group Split Contexts (新增) ---
+
+#[derive(Accounts)]
+#[instruction(title: String, total_amount: u64, member_count: u8, ipfs_hash: String, timestamp: i64)]
+pub struct CreateGroupSplit<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = GroupSplit::LEN,
+        seeds = [
+            b"group_split",
+            creator.key().as_ref(),
+            &timestamp.to_le_bytes()
+        ],
+        bump
+    )]
+    pub group_split: Account<'info, GroupSplit>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(member_pubkey: Pubkey)]
+pub struct AddSplitMember<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"group_split",
+            group_split.creator.as_ref(),
+            &group_split.created_at.to_le_bytes()
+        ],
+        bump = group_split.bump
+    )]
+    pub group_split: Account<'info, GroupSplit>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = SplitMember::LEN,
+        seeds = [
+            b"split_member",
+            group_split.key().as_ref(),
+            member_pubkey.as_ref()
+        ],
+        bump
+    )]
+    pub split_member: Account<'info, SplitMember>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MarkSplitPaid<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"group_split",
+            group_split.creator.as_ref(),
+            &group_split.created_at.to_le_bytes()
+        ],
+        bump = group_split.bump
+    )]
+    pub group_split: Account<'info, GroupSplit>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"split_member",
+            group_split.key().as_ref(),
+            split_member.member.as_ref()
+        ],
+        bump = split_member.bump
+    )]
+    pub split_member: Account<'info, SplitMember>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pet", payer.key().as_ref()],
+        bump = pet_state.bump,
+        constraint = pet_state.owner == payer.key() @ ErrorCode::Unauthorized
+    )]
+    pub pet_state: Option<Account<'info, PetState>>,
+
+    #[account(seeds = [b"reward_config"], bump = reward_config.bump)]
+    pub reward_config: Option<Account<'info, RewardConfig>>,
+}
+
+#[derive(Accounts)]
+pub struct CloseGroupSplit<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"group_split",
+            group_split.creator.as_ref(),
+            &group_split.created_at.to_le_bytes()
+        ],
+        bump = group_split.bump
+    )]
+    pub group_split: Account<'info, GroupSplit>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+}
+
+// --- Pet System Contexts (新增) ---
+
+#[derive(Accounts)]
+pub struct InitializePet<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = PetState::LEN,
+        seeds = [b"pet", user.key().as_ref()],
+        bump
+    )]
+    pub pet_state: Account<'info, PetState>,
+
+    #[account(
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump = user_profile.bump,
+        constraint = user_profile.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FeedPet<'info> {
+    #[account(
+        mut,
+        seeds = [b"pet", user.key().as_ref()],
+        bump = pet_state.bump,
+        constraint = pet_state.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub pet_state: Account<'info, PetState>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PlayWithPet<'info> {
+    #[account(
+        mut,
+        seeds = [b"pet", user.key().as_ref()],
+        bump = pet_state.bump,
+        constraint = pet_state.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub pet_state: Account<'info, PetState>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct EvolvePet<'info> {
+    #[account(
+        mut,
+        seeds = [b"pet", user.key().as_ref()],
+        bump = pet_state.bump,
+        constraint = pet_state.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub pet_state: Account<'info, PetState>,
+
+    #[account(
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump = user_profile.bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(
+        seeds = [b"expense_stats", user.key().as_ref()],
+        bump = expense_stats.bump
+    )]
+    pub expense_stats: Option<Account<'info, ExpenseStats>>,
+
+    pub user: Signer<'info>,
 }
 
-#[account]
-pub struct Message {
-    pub chat_room: Pubkey,
-    pub sender: Pubkey,
-    pub content: String,
-    pub message_index: u64,
-    pub timestamp: i64,
-    pub bump: u8,
+#[derive(Accounts)]
+pub struct MintPetNft<'info> {
+    #[account(
+        mut,
+        seeds = [b"pet", user.key().as_ref()],
+        bump = pet_state.bump,
+        constraint = pet_state.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub pet_state: Account<'info, PetState>,
+
+    #[account(
+        init,
+        payer = user,
+        seeds = [
+            b"pet_mint",
+            user.key().as_ref(),
+            &pet_state.created_at.to_le_bytes()
+        ],
+        bump,
+        mint::decimals = 0,
+        mint::authority = nft_authority,
+        mint::freeze_authority = nft_authority
+    )]
+    pub pet_mint: Account<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = pet_mint,
+        associated_token::authority = user
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Metaplex metadata PDA, created by the token metadata program
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), pet_mint.key().as_ref()],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub metadata: UncheckedAccount<'info>,
+
+    /// CHECK: Metaplex master edition PDA, created by the token metadata program
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            pet_mint.key().as_ref(),
+            b"edition"
+        ],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub master_edition: UncheckedAccount<'info>,
+
+    /// CHECK: Program-wide PDA acting as mint and update authority for pet NFTs
+    #[account(seeds = [b"pet_nft_authority"], bump)]
+    pub nft_authority: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
 }
 
-impl Message {
-    pub const LEN: usize = 8 + 32 + 32 + 504 + 8 + 8 + 1;
+#[derive(Accounts)]
+pub struct RefreshPetNft<'info> {
+    #[account(
+        seeds = [b"pet", user.key().as_ref()],
+        bump = pet_state.bump,
+        constraint = pet_state.owner == user.key() @ ErrorCode::Unauthorized,
+        constraint = pet_state.nft_mint != Pubkey::default() @ ErrorCode::PetNotMinted
+    )]
+    pub pet_state: Account<'info, PetState>,
+
+    /// CHECK: Metaplex metadata PDA of the pet mint
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            pet_state.nft_mint.as_ref()
+        ],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub metadata: UncheckedAccount<'info>,
+
+    /// CHECK: Program-wide PDA acting as mint and update authority for pet NFTs
+    #[account(seeds = [b"pet_nft_authority"], bump)]
+    pub nft_authority: UncheckedAccount<'info>,
+
+    pub user: Signer<'info>,
+
+    pub token_metadata_program: Program<'info, Metadata>,
 }
 
-#[account]
-pub struct ExpenseRecord {
-    pub owner: Pubkey,
-    pub recipient: Pubkey,
-    pub amount: u64,
-    pub category: ExpenseCategory,
-    pub description: String,
-    pub timestamp: i64,
-    pub tx_signature: String,
-    pub record_index: u64,
-    pub bump: u8,
+#[derive(Accounts)]
+pub struct SyncPetNftOwner<'info> {
+    #[account(
+        mut,
+        close = previous_owner,
+        seeds = [b"pet", pet_state.owner.as_ref()],
+        bump = pet_state.bump,
+        constraint = pet_state.nft_mint != Pubkey::default() @ ErrorCode::PetNotMinted,
+        constraint = pet_state.owner != new_owner.key() @ ErrorCode::PetAlreadyOwned
+    )]
+    pub pet_state: Account<'info, PetState>,
+
+    /// CHECK: Previous owner, receives the rent of the old pet account
+    #[account(mut, address = pet_state.owner)]
+    pub previous_owner: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", pet_state.owner.as_ref()],
+        bump = previous_profile.bump
+    )]
+    pub previous_profile: Account<'info, UserProfile>,
+
+    // 已存在时在 handler 中返回 NewOwnerHasPet，而不是 init 的通用错误
+    #[account(
+        init_if_needed,
+        payer = new_owner,
+        space = PetState::LEN,
+        seeds = [b"pet", new_owner.key().as_ref()],
+        bump
+    )]
+    pub new_pet_state: Account<'info, PetState>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", new_owner.key().as_ref()],
+        bump = new_profile.bump
+    )]
+    pub new_profile: Account<'info, UserProfile>,
+
+    #[account(
+        token::mint = pet_state.nft_mint,
+        token::authority = new_owner,
+        constraint = holder_token_account.amount == 1 @ ErrorCode::PetNftNotHeld
+    )]
+    pub holder_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub new_owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
-impl ExpenseRecord {
-    pub const LEN: usize = 8 + 32 + 32 + 8 + 1 + 104 + 8 + 92 + 8 + 1;
+#[derive(Accounts)]
+pub struct ReleasePet<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"pet", owner.key().as_ref()],
+        bump = pet_state.bump,
+        constraint = pet_state.nft_mint == Pubkey::default() @ ErrorCode::PetAlreadyMinted
+    )]
+    pub pet_state: Account<'info, PetState>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", owner.key().as_ref()],
+        bump = owner_profile.bump
+    )]
+    pub owner_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
 }
 
-#[account]
-pub struct ExpenseStats {
-    pub owner: Pubkey,
-    pub total_spent: u64,
-    pub record_count: u64,
-    pub dining_total: u64,
-    pub shopping_total: u64,
-    pub entertainment_total: u64,
-    pub travel_total: u64,
-    pub gifts_total: u64,
-    pub bills_total: u64,
-    pub other_total: u64,
-    pub last_updated: i64,
-    pub bump: u8,
+#[derive(Accounts)]
+pub struct ReleaseBurnedPet<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"pet", pet_state.owner.as_ref()],
+        bump = pet_state.bump,
+        constraint = pet_state.nft_mint != Pubkey::default() @ ErrorCode::PetNotMinted
+    )]
+    pub pet_state: Account<'info, PetState>,
+
+    #[account(
+        address = pet_state.nft_mint,
+        constraint = pet_mint.supply == 0 @ ErrorCode::PetNftNotBurned
+    )]
+    pub pet_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", pet_state.owner.as_ref()],
+        bump = owner_profile.bump
+    )]
+    pub owner_profile: Account<'info, UserProfile>,
+
+    /// CHECK: Pet owner, receives the rent of the released pet account
+    #[account(mut, address = pet_state.owner)]
+    pub owner: AccountInfo<'info>,
 }
 
-impl ExpenseStats {
-    pub const LEN: usize = 8 + 32 + 8 + 8 + (8 * 7) + 8 + 1;
+#[derive(Accounts)]
+pub struct GetPetMultipliers<'info> {
+    #[account(seeds = [b"pet", pet_state.owner.as_ref()], bump = pet_state.bump)]
+    pub pet_state: Account<'info, PetState>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
-pub enum ExpenseCategory {
-    Dining,
-    Shopping,
-    Entertainment,
-    Travel,
-    Gifts,
-    Bills,
-    Other,
+#[derive(Accounts)]
+pub struct InitializeRewardConfig<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = RewardConfig::LEN,
+        seeds = [b"reward_config"],
+        bump
+    )]
+    pub reward_config: Account<'info, RewardConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        constraint = program.programdata_address()? == Some(program_data.key())
+            @ ErrorCode::Unauthorized
+    )]
+    pub program: Program<'info, crate::program::SolamateProgram>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(authority.key())
+            @ ErrorCode::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
 }
 
-// 新增：福利活动账户
-#[account]
-pub struct FundingEvent {
-    pub creator: Pubkey,
-    pub title: String,
-    pub total_amount: u64,
-    pub remaining_amount: u64,
-    pub deadline: i64,
-    pub ipfs_hash: String,
-    pub status: EventStatus,
-    pub created_at: i64,
-    pub application_count: u32,
-    pub approved_count: u32,
-    pub bump: u8,
+#[derive(Accounts)]
+pub struct UpdateRewardConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"reward_config"],
+        bump = reward_config.bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub reward_config: Account<'info, RewardConfig>,
+
+    pub authority: Signer<'info>,
 }
 
-impl FundingEvent {
-    pub const LEN: usize = 8 + 32 + 68 + 8 + 8 + 8 + 68 + 1 + 8 + 4 + 4 + 1;
+// --- Daily Quest Contexts (新增) ---
+
+#[derive(Accounts)]
+pub struct InitializeQuestProgress<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = QuestProgress::LEN,
+        seeds = [b"quest_progress", user.key().as_ref()],
+        bump
+    )]
+    pub quest_progress: Account<'info, QuestProgress>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
-// 新增：申请账户
-#[account]
-pub struct Application {
-    pub event: Pubkey,
-    pub applicant: Pubkey,
-    pub requested_amount: u64,
-    pub approved_amount: u64,
-    pub ipfs_hash: String,
-    pub status: ApplicationStatus,
-    pub applied_at: i64,
-    pub bump: u8,
+#[derive(Accounts)]
+pub struct ClaimQuestReward<'info> {
+    #[account(
+        mut,
+        seeds = [b"quest_progress", user.key().as_ref()],
+        bump = quest_progress.bump,
+        constraint = quest_progress.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub quest_progress: Account<'info, QuestProgress>,
+
+    #[account(
+        mut,
+        seeds = [b"pet", user.key().as_ref()],
+        bump = pet_state.bump,
+        constraint = pet_state.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub pet_state: Account<'info, PetState>,
+
+    pub user: Signer<'info>,
 }
 
-impl Application {
-    pub const LEN: usize = 8 + 32 + 32 + 8 + 8 + 68 + 1 + 8 + 1;
+// --- Circle Contexts (新增) ---
+
+#[derive(Accounts)]
+#[instruction(name: String, timestamp: i64)]
+pub struct CreateCircle<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = Circle::LEN,
+        seeds = [
+            b"circle",
+            owner.key().as_ref(),
+            &timestamp.to_le_bytes()
+        ],
+        bump
+    )]
+    pub circle: Account<'info, Circle>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
-// 新增：活动状态
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
-pub enum EventStatus {
-    Active,
-    Closed,
+#[derive(Accounts)]
+pub struct InviteToCircle<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"circle",
+            circle.owner.as_ref(),
+            &circle.created_at.to_le_bytes()
+        ],
+        bump = circle.bump
+    )]
+    pub circle: Account<'info, Circle>,
+
+    pub user: Signer<'info>,
 }
 
-// 新增：申请状态
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
-pub enum ApplicationStatus {
-    Pending,
-    Approved,
-    Rejected,
-    Paid,
+#[derive(Accounts)]
+pub struct JoinCircle<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"circle",
+            circle.owner.as_ref(),
+            &circle.created_at.to_le_bytes()
+        ],
+        bump = circle.bump
+    )]
+    pub circle: Account<'info, Circle>,
+
+    pub user: Signer<'info>,
 }
 
-// 新增：分账群组账户
-#[account]
-pub struct GroupSplit {
-    pub creator: Pubkey,
-    pub title: String,
-    pub total_amount: u64,
-    pub member_count: u8,
-    pub amount_per_person: u64,
-    pub ipfs_hash: String,
-    pub created_at: i64,
-    pub settled_count: u32,
-    pub status: SplitStatus,
-    pub bump: u8,
+#[derive(Accounts)]
+pub struct LeaveCircle<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"circle",
+            circle.owner.as_ref(),
+            &circle.created_at.to_le_bytes()
+        ],
+        bump = circle.bump
+    )]
+    pub circle: Account<'info, Circle>,
+
+    pub user: Signer<'info>,
 }
 
-impl GroupSplit {
-    pub const LEN: usize = 8 + 32 + 68 + 8 + 1 + 8 + 68 + 8 + 4 + 1 + 1;
-}
+#[derive(Accounts)]
+pub struct RemoveCircleMember<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"circle",
+            circle.owner.as_ref(),
+            &circle.created_at.to_le_bytes()
+        ],
+        bump = circle.bump
+    )]
+    pub circle: Account<'info, Circle>,
 
-// 新增：分账成员账户
-#[account]
-pub struct SplitMember {
-    pub split: Pubkey,
-    pub member: Pubkey,
-    pub amount_owed: u64,
-    pub paid: bool,
-    pub paid_at: i64,
-    pub bump: u8,
+    pub user: Signer<'info>,
 }
 
-impl SplitMember {
-    pub const LEN: usize = 8 + 32 + 32 + 8 + 1 + 8 + 1;
-}
+#[derive(Accounts)]
+pub struct SetCircleRole<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"circle",
+            circle.owner.as_ref(),
+            &circle.created_at.to_le_bytes()
+        ],
+        bump = circle.bump,
+        has_one = owner @ ErrorCode::Unauthorized
+    )]
+    pub circle: Account<'info, Circle>,
 
-// 新增：分账状态
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
-pub enum SplitStatus {
-    Active,
-    Settled,
-    Closed,
+    pub owner: Signer<'info>,
 }
 
-// ============================================================================
-// CONTEXT STRUCTURES - 上下文结构
-// ============================================================================
+#[derive(Accounts)]
+pub struct DissolveCircle<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [
+            b"circle",
+            circle.owner.as_ref(),
+            &circle.created_at.to_le_bytes()
+        ],
+        bump = circle.bump,
+        has_one = owner @ ErrorCode::Unauthorized
+    )]
+    pub circle: Account<'info, Circle>,
 
-// --- Social Program Contexts ---
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+// --- Group Chat Contexts (新增) ---
 
 #[derive(Accounts)]
-#[instruction(username: String, avatar: String)]
-pub struct InitializeProfile<'info> {
+#[instruction(name: String, topic: Pubkey, timestamp: i64)]
+pub struct CreateGroupChat<'info> {
     #[account(
         init,
-        payer = user,
-        space = UserProfile::LEN,
-        seeds = [b"user_profile", user.key().as_ref()],
+        payer = owner,
+        space = GroupChatRoom::LEN,
+        seeds = [
+            b"group_chat",
+            owner.key().as_ref(),
+            &timestamp.to_le_bytes()
+        ],
         bump
     )]
-    pub user_profile: Account<'info, UserProfile>,
+    pub group_chat: Account<'info, GroupChatRoom>,
 
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub owner: Signer<'info>,
+
+    /// CHECK: Split, funding event or circle named by `topic`; validated in the handler
+    pub topic_account: Option<UncheckedAccount<'info>>,
 
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct UpdateProfile<'info> {
+pub struct InviteToGroupChat<'info> {
     #[account(
         mut,
-        seeds = [b"user_profile", user.key().as_ref()],
-        bump = user_profile.bump,
-        constraint = user_profile.owner == user.key() @ ErrorCode::Unauthorized
+        seeds = [
+            b"group_chat",
+            group_chat.owner.as_ref(),
+            &group_chat.created_at.to_le_bytes()
+        ],
+        bump = group_chat.bump
     )]
-    pub user_profile: Account<'info, UserProfile>,
+    pub group_chat: Account<'info, GroupChatRoom>,
 
-    #[account(mut)]
     pub user: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct SelectPet<'info> {
+pub struct JoinGroupChat<'info> {
     #[account(
         mut,
-        seeds = [b"user_profile", user.key().as_ref()],
-        bump = user_profile.bump,
-        has_one = owner @ ErrorCode::Unauthorized
+        seeds = [
+            b"group_chat",
+            group_chat.owner.as_ref(),
+            &group_chat.created_at.to_le_bytes()
+        ],
+        bump = group_chat.bump
     )]
-    pub user_profile: Account<'info, UserProfile>,
+    pub group_chat: Account<'info, GroupChatRoom>,
 
-    #[account(mut)]
     pub user: Signer<'info>,
-
-    /// CHECK: This is the owner field in user_profile
-    pub owner: AccountInfo<'info>,
 }
 
 #[derive(Accounts)]
-#[instruction(user_a: Pubkey, user_b: Pubkey)]
-pub struct SendFriendRequest<'info> {
+pub struct KickFromGroupChat<'info> {
     #[account(
-        init,
-        payer = user,
-        space = Friendship::LEN,
-        seeds = [b"friendship", user_a.as_ref(), user_b.as_ref()],
-        bump
+        mut,
+        seeds = [
+            b"group_chat",
+            group_chat.owner.as_ref(),
+            &group_chat.created_at.to_le_bytes()
+        ],
+        bump = group_chat.bump
     )]
-    pub friendship: Account<'info, Friendship>,
+    pub group_chat: Account<'info, GroupChatRoom>,
 
-    #[account(mut)]
     pub user: Signer<'info>,
+}
 
-    /// CHECK: Friend's public key
-    pub friend: AccountInfo<'info>,
+#[derive(Accounts)]
+pub struct LeaveGroupChat<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"group_chat",
+            group_chat.owner.as_ref(),
+            &group_chat.created_at.to_le_bytes()
+        ],
+        bump = group_chat.bump
+    )]
+    pub group_chat: Account<'info, GroupChatRoom>,
 
-    pub system_program: Program<'info, System>,
+    pub user: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct AcceptFriendRequest<'info> {
+pub struct SetGroupChatRole<'info> {
     #[account(
         mut,
         seeds = [
-            b"friendship",
-            friendship.user_a.as_ref(),
-            friendship.user_b.as_ref()
+            b"group_chat",
+            group_chat.owner.as_ref(),
+            &group_chat.created_at.to_le_bytes()
         ],
-        bump = friendship.bump
+        bump = group_chat.bump,
+        has_one = owner @ ErrorCode::Unauthorized
     )]
-    pub friendship: Account<'info, Friendship>,
+    pub group_chat: Account<'info, GroupChatRoom>,
+
+    pub owner: Signer<'info>,
+}
 
+#[derive(Accounts)]
+pub struct SendGroupMessage<'info> {
     #[account(
-        init_if_needed,
-        payer = user,
-        space = UserProfile::LEN,
-        seeds = [b"user_profile", friendship.user_a.as_ref()],
-        bump
+        mut,
+        seeds = [
+            b"group_chat",
+            group_chat.owner.as_ref(),
+            &group_chat.created_at.to_le_bytes()
+        ],
+        bump = group_chat.bump
     )]
-    pub user_a_profile: Account<'info, UserProfile>,
+    pub group_chat: Account<'info, GroupChatRoom>,
 
     #[account(
-        init_if_needed,
-        payer = user,
-        space = UserProfile::LEN,
-        seeds = [b"user_profile", friendship.user_b.as_ref()],
+        init,
+        payer = sender,
+        space = Message::LEN,
+        seeds = [
+            b"message",
+            group_chat.key().as_ref(),
+            group_chat.message_count.to_le_bytes().as_ref()
+        ],
         bump
     )]
-    pub user_b_profile: Account<'info, UserProfile>,
+    pub message: Account<'info, Message>,
 
     #[account(mut)]
-    pub user: Signer<'info>,
-
-    pub system_program: Program<'info, System>,
-}
+    pub sender: Signer<'info>,
 
-#[derive(Accounts)]
-pub struct RemoveFriend<'info> {
+    /// CHECK: Block PDA created by the group owner against the sender; must not exist
     #[account(
-        mut,
-        close = user,
-        seeds = [
-            b"friendship",
-            friendship.user_a.as_ref(),
-            friendship.user_b.as_ref()
-        ],
-        bump = friendship.bump
+        seeds = [b"block", group_chat.owner.as_ref(), sender.key().as_ref()],
+        bump
     )]
-    pub friendship: Account<'info, Friendship>,
+    pub owner_block: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
 
     #[account(
         mut,
-        seeds = [b"user_profile", friendship.user_a.as_ref()],
-        bump = user_a_profile.bump
+        seeds = [b"pet", sender.key().as_ref()],
+        bump = pet_state.bump,
+        constraint = pet_state.owner == sender.key() @ ErrorCode::Unauthorized
     )]
-    pub user_a_profile: Account<'info, UserProfile>,
+    pub pet_state: Option<Account<'info, PetState>>,
+
+    #[account(seeds = [b"reward_config"], bump = reward_config.bump)]
+    pub reward_config: Option<Account<'info, RewardConfig>>,
 
     #[account(
         mut,
-        seeds = [b"user_profile", friendship.user_b.as_ref()],
-        bump = user_b_profile.bump
+        seeds = [b"quest_progress", sender.key().as_ref()],
+        bump = quest_progress.bump
     )]
-    pub user_b_profile: Account<'info, UserProfile>,
+    pub quest_progress: Option<Account<'info, QuestProgress>>,
+}
+
+// --- Migration Contexts (新增) ---
 
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    /// CHECK: Any v0 program account; owner and discriminator are checked in migrate_account
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub account: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
-// --- Chat Program Contexts ---
+// ============================================================================
+// EVENTS - 事件
+// ============================================================================
 
-#[derive(Accounts)]
-pub struct InitializeChatRoom<'info> {
-    #[account(
-        init,
-        payer = payer,
-        space = ChatRoom::LEN,
-        seeds = [b"chat_room", user_a.key().as_ref(), user_b.key().as_ref()],
-        bump
-    )]
-    pub chat_room: Account<'info, ChatRoom>,
+// --- Profile Events (新增) ---
 
-    /// CHECK: User A's public key
-    pub user_a: AccountInfo<'info>,
+#[event]
+pub struct ProfileCreated {
+    pub owner: Pubkey,
+    pub username: String,
+    pub avatar: String,
+    pub created_at: i64,
+}
 
-    /// CHECK: User B's public key
-    pub user_b: AccountInfo<'info>,
+#[event]
+pub struct ProfileUpdated {
+    pub owner: Pubkey,
+    pub username: String,
+    pub avatar: String,
+}
+
+#[event]
+pub struct UsernameChanged {
+    pub owner: Pubkey,
+    pub old_username: String,
+    pub new_username: String,
+}
+
+#[event]
+pub struct ProfileDetailsUpdated {
+    pub owner: Pubkey,
+    pub image_uri: String,
+    pub link_count: u8,
+    pub updated_at: i64,
+}
+
+#[event]
+pub struct ProfileClosed {
+    pub owner: Pubkey,
+}
+
+#[event]
+pub struct PetSelected {
+    pub owner: Pubkey,
+    pub pet_id: u8,
+}
+
+// --- Social Events (新增) ---
+
+#[event]
+pub struct FriendRequestSent {
+    pub friendship: Pubkey,
+    pub requester: Pubkey,
+    pub recipient: Pubkey,
+    pub created_at: i64,
+}
+
+#[event]
+pub struct FriendRequestRejected {
+    pub friendship: Pubkey,
+    pub requester: Pubkey,
+    pub recipient: Pubkey,
+}
+
+#[event]
+pub struct FriendRequestCancelled {
+    pub friendship: Pubkey,
+    pub requester: Pubkey,
+    pub recipient: Pubkey,
+}
+
+#[event]
+pub struct FriendshipAccepted {
+    pub friendship: Pubkey,
+    pub user_a: Pubkey,
+    pub user_b: Pubkey,
+    pub accepted_at: i64,
+}
+
+#[event]
+pub struct FriendRemoved {
+    pub friendship: Pubkey,
+    pub user_a: Pubkey,
+    pub user_b: Pubkey,
+    pub removed_by: Pubkey,
+}
+
+#[event]
+pub struct UserBlocked {
+    pub blocker: Pubkey,
+    pub blocked: Pubkey,
+    pub friendship_removed: bool,
+    pub created_at: i64,
+}
+
+#[event]
+pub struct UserUnblocked {
+    pub blocker: Pubkey,
+    pub blocked: Pubkey,
+}
+
+// --- Chat Events (新增) ---
+
+#[event]
+pub struct ChatRoomCreated {
+    pub chat_room: Pubkey,
+    pub user_a: Pubkey,
+    pub user_b: Pubkey,
+}
+
+#[event]
+pub struct ChatConfigUpdated {
+    pub authority: Pubkey,
+    pub require_friendship: bool,
+    pub edit_window_secs: i64,
+}
+
+#[event]
+pub struct MessageSent {
+    pub chat_room: Pubkey,
+    pub message: Pubkey,
+    pub sender: Pubkey,
+    pub message_index: u64,
+    pub timestamp: i64,
+    pub encrypted: bool,
+}
+
+#[event]
+pub struct EncryptionKeyPublished {
+    pub owner: Pubkey,
+    pub public_key: [u8; 32],
+    pub key_version: u32,
+}
+
+#[event]
+pub struct MessageDeleted {
+    pub chat_room: Pubkey,
+    pub message: Pubkey,
+    pub sender: Pubkey,
+    pub message_index: u64,
+}
+
+#[event]
+pub struct MessageEdited {
+    pub chat_room: Pubkey,
+    pub message: Pubkey,
+    pub sender: Pubkey,
+    pub message_index: u64,
+    pub edit_count: u16,
+    pub previous_content_hash: [u8; 32],
+    pub edited_at: i64,
+}
+
+#[event]
+pub struct ChatPageOpened {
+    pub chat_room: Pubkey,
+    pub page: Pubkey,
+    pub page_index: u32,
+    pub payer: Pubkey,
+}
 
-    #[account(mut)]
-    pub payer: Signer<'info>,
+#[event]
+pub struct PagedMessageSent {
+    pub chat_room: Pubkey,
+    pub page: Pubkey,
+    pub page_index: u32,
+    pub sender: Pubkey,
+    pub message_index: u64,
+    pub timestamp: i64,
+}
 
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct ChatRetentionUpdated {
+    pub chat_room: Pubkey,
+    pub updated_by: Pubkey,
+    pub retained_pages: u16,
 }
 
-#[derive(Accounts)]
-pub struct SendMessage<'info> {
-    #[account(
-        mut,
-        seeds = [
-            b"chat_room",
-            chat_room.user_a.as_ref(),
-            chat_room.user_b.as_ref()
-        ],
-        bump = chat_room.bump
-    )]
-    pub chat_room: Account<'info, ChatRoom>,
+#[event]
+pub struct ChatPageReclaimed {
+    pub chat_room: Pubkey,
+    pub page: Pubkey,
+    pub page_index: u32,
+    pub payer: Pubkey,
+}
 
-    #[account(
-        init,
-        payer = sender,
-        space = Message::LEN,
-        seeds = [
-            b"message",
-            chat_room.key().as_ref(),
-            chat_room.message_count.to_le_bytes().as_ref()
-        ],
-        bump
-    )]
-    pub message: Account<'info, Message>,
+#[event]
+pub struct ChatTreeInitialized {
+    pub chat_room: Pubkey,
+    pub chat_tree: Pubkey,
+    pub max_depth: u32,
+    pub max_buffer_size: u32,
+}
 
-    #[account(mut)]
-    pub sender: Signer<'info>,
+#[event]
+pub struct CompressedMessageSent {
+    pub chat_room: Pubkey,
+    pub sender: Pubkey,
+    pub message_index: u64,
+    pub leaf_index: u64,
+    pub leaf: [u8; 32],
+    pub tree_root: [u8; 32],
+    pub timestamp: i64,
+}
 
-    pub system_program: Program<'info, System>,
+// --- Expense Events (新增) ---
+
+#[event]
+pub struct ExpenseStatsInitialized {
+    pub owner: Pubkey,
 }
 
-#[derive(Accounts)]
-pub struct DeleteMessage<'info> {
-    #[account(
-        mut,
-        close = sender,
-        seeds = [
-            b"message",
-            message.chat_room.as_ref(),
-            message.message_index.to_le_bytes().as_ref()
-        ],
-        bump = message.bump,
-        has_one = sender @ ErrorCode::Unauthorized
-    )]
-    pub message: Account<'info, Message>,
+#[event]
+pub struct ExpenseRecorded {
+    pub owner: Pubkey,
+    pub recipient: Pubkey,
+    pub expense_record: Pubkey,
+    pub amount: u64,
+    pub category: ExpenseCategory,
+    pub record_index: u64,
+    pub timestamp: i64,
+}
 
-    #[account(mut)]
-    pub sender: Signer<'info>,
+#[event]
+pub struct ExpenseRecordDeleted {
+    pub owner: Pubkey,
+    pub expense_record: Pubkey,
+    pub amount: u64,
+    pub category: ExpenseCategory,
+    pub record_index: u64,
 }
 
-// --- Expense Program Contexts ---
+#[event]
+pub struct ExpenseStatsClosed {
+    pub owner: Pubkey,
+}
 
-#[derive(Accounts)]
-pub struct InitializeExpenseStats<'info> {
-    #[account(
-        init,
-        payer = user,
-        space = ExpenseStats::LEN,
-        seeds = [b"expense_stats", user.key().as_ref()],
-        bump
-    )]
-    pub expense_stats: Account<'info, ExpenseStats>,
+// --- Funding Events (新增) ---
 
-    #[account(mut)]
-    pub user: Signer<'info>,
+#[event]
+pub struct FundingEventCreated {
+    pub funding_event: Pubkey,
+    pub creator: Pubkey,
+    pub total_amount: u64,
+    pub deadline: i64,
+}
 
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct FundingApplied {
+    pub funding_event: Pubkey,
+    pub application: Pubkey,
+    pub applicant: Pubkey,
+    pub requested_amount: u64,
 }
 
-#[derive(Accounts)]
-pub struct RecordExpense<'info> {
-    #[account(
-        init,
-        payer = user,
-        space = ExpenseRecord::LEN,
-        seeds = [
-            b"expense_record",
-            user.key().as_ref(),
-            expense_stats.record_count.to_le_bytes().as_ref()
-        ],
-        bump
-    )]
-    pub expense_record: Account<'info, ExpenseRecord>,
+#[event]
+pub struct ApplicationApproved {
+    pub funding_event: Pubkey,
+    pub application: Pubkey,
+    pub applicant: Pubkey,
+    pub approved_amount: u64,
+}
 
-    #[account(
-        mut,
-        seeds = [b"expense_stats", user.key().as_ref()],
-        bump = expense_stats.bump,
-        has_one = owner @ ErrorCode::Unauthorized
-    )]
-    pub expense_stats: Account<'info, ExpenseStats>,
+#[event]
+pub struct ApplicationRejected {
+    pub funding_event: Pubkey,
+    pub application: Pubkey,
+    pub applicant: Pubkey,
+}
 
-    /// CHECK: This is the owner field in expense_stats
-    pub owner: AccountInfo<'info>,
+#[event]
+pub struct FundsDisbursed {
+    pub funding_event: Pubkey,
+    pub application: Pubkey,
+    pub applicant: Pubkey,
+    pub amount: u64,
+    pub remaining_amount: u64,
+}
 
-    /// CHECK: Recipient's public key
-    pub recipient: AccountInfo<'info>,
+#[event]
+pub struct FundingEventClosed {
+    pub funding_event: Pubkey,
+    pub creator: Pubkey,
+    pub refunded_amount: u64,
+}
 
-    #[account(mut)]
-    pub user: Signer<'info>,
+// --- Group Split Events (新增) ---
 
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct GroupSplitCreated {
+    pub group_split: Pubkey,
+    pub creator: Pubkey,
+    pub total_amount: u64,
+    pub member_count: u8,
+    pub amount_per_person: u64,
 }
 
-#[derive(Accounts)]
-pub struct DeleteExpenseRecord<'info> {
-    #[account(
-        mut,
-        close = user,
-        seeds = [
-            b"expense_record",
-            expense_record.owner.as_ref(),
-            expense_record.record_index.to_le_bytes().as_ref()
-        ],
-        bump = expense_record.bump,
-        has_one = owner @ ErrorCode::Unauthorized
-    )]
-    pub expense_record: Account<'info, ExpenseRecord>,
-
-    #[account(
-        mut,
-        seeds = [b"expense_stats", user.key().as_ref()],
-        bump = expense_stats.bump
-    )]
-    pub expense_stats: Account<'info, ExpenseStats>,
+#[event]
+pub struct SplitMemberAdded {
+    pub group_split: Pubkey,
+    pub member: Pubkey,
+    pub amount_owed: u64,
+}
 
-    /// CHECK: This is the owner field in expense_record
-    pub owner: AccountInfo<'info>,
+#[event]
+pub struct SplitPaid {
+    pub group_split: Pubkey,
+    pub member: Pubkey,
+    pub marked_by: Pubkey,
+    pub amount: u64,
+    pub paid_at: i64,
+    pub settled: bool, // 本次付款后分账是否已结清
+}
 
-    #[account(mut)]
-    pub user: Signer<'info>,
+#[event]
+pub struct GroupSplitClosed {
+    pub group_split: Pubkey,
+    pub creator: Pubkey,
 }
 
-// --- Funding Events Contexts (新增) ---
+// --- Pet Events (新增) ---
 
-#[derive(Accounts)]
-#[instruction(title: String, amount: u64, deadline: i64, ipfs_hash: String, timestamp: i64)]
-pub struct CreateFundingEvent<'info> {
-    #[account(
-        init,
-        payer = creator,
-        space = FundingEvent::LEN,
-        seeds = [
-            b"funding_event",
-            creator.key().as_ref(),
-            &timestamp.to_le_bytes()
-        ],
-        bump
-    )]
-    pub funding_event: Account<'info, FundingEvent>,
+#[event]
+pub struct PetHatched {
+    pub owner: Pubkey,
+    pub pet_id: u8,
+    pub created_at: i64,
+}
 
-    #[account(mut)]
-    pub creator: Signer<'info>,
+#[event]
+pub struct PetCared {
+    pub owner: Pubkey,
+    pub action: PetCareAction,
+    pub xp_earned: u32,
+    pub level: u8,
+    pub leveled_up: bool,
+    pub happiness: u8,
+    pub energy: u8,
+}
 
-    pub system_program: Program<'info, System>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PetCareAction {
+    Feed,
+    Play,
 }
 
-#[derive(Accounts)]
-pub struct ApplyForFunding<'info> {
-    #[account(
-        mut,
-        seeds = [
-            b"funding_event",
-            funding_event.creator.as_ref(),
-            &funding_event.created_at.to_le_bytes()
-        ],
-        bump = funding_event.bump
-    )]
-    pub funding_event: Account<'info, FundingEvent>,
+#[event]
+pub struct PetXpEarned {
+    pub owner: Pubkey,
+    pub action: RewardAction,
+    pub xp_earned: u32, // 受每日上限影响，可能为 0
+    pub level: u8,
+    pub xp: u32,
+}
 
-    #[account(
-        init,
-        payer = applicant,
-        space = Application::LEN,
-        seeds = [
-            b"application",
-            funding_event.key().as_ref(),
-            applicant.key().as_ref()
-        ],
-        bump
-    )]
-    pub application: Account<'info, Application>,
+#[event]
+pub struct RewardConfigUpdated {
+    pub authority: Pubkey,
+    pub xp_rewards: [u32; REWARD_ACTION_COUNT],
+    pub daily_caps: [u32; REWARD_ACTION_COUNT],
+}
 
-    #[account(mut)]
-    pub applicant: Signer<'info>,
+#[event]
+pub struct PetEvolved {
+    pub owner: Pubkey,
+    pub pet_id: u8,
+    pub stage: PetStage,
+    pub level: u8,
+    pub evolved_at: i64,
+}
 
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct PetNftMinted {
+    pub owner: Pubkey,
+    pub pet_id: u8,
+    pub mint: Pubkey,
 }
 
-#[derive(Accounts)]
-pub struct ApproveApplication<'info> {
-    #[account(
-        mut,
-        seeds = [
-            b"funding_event",
-            funding_event.creator.as_ref(),
-            &funding_event.created_at.to_le_bytes()
-        ],
-        bump = funding_event.bump
-    )]
-    pub funding_event: Account<'info, FundingEvent>,
+#[event]
+pub struct PetNftRefreshed {
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+}
 
-    #[account(
-        mut,
-        seeds = [
-            b"application",
-            funding_event.key().as_ref(),
-            application.applicant.as_ref()
-        ],
-        bump = application.bump
-    )]
-    pub application: Account<'info, Application>,
+#[event]
+pub struct PetNftOwnerSynced {
+    pub mint: Pubkey,
+    pub previous_owner: Pubkey,
+    pub new_owner: Pubkey,
+}
 
-    pub creator: Signer<'info>,
+#[event]
+pub struct BurnedPetReleased {
+    pub owner: Pubkey,
+    pub mint: Pubkey,
 }
 
-#[derive(Accounts)]
-pub struct RejectApplication<'info> {
-    #[account(
-        seeds = [
-            b"funding_event",
-            funding_event.creator.as_ref(),
-            &funding_event.created_at.to_le_bytes()
-        ],
-        bump = funding_event.bump
-    )]
-    pub funding_event: Account<'info, FundingEvent>,
+#[event]
+pub struct PetReleased {
+    pub owner: Pubkey,
+    pub pet_id: u8,
+}
 
-    #[account(
-        mut,
-        seeds = [
-            b"application",
-            funding_event.key().as_ref(),
-            application.applicant.as_ref()
-        ],
-        bump = application.bump
-    )]
-    pub application: Account<'info, Application>,
+// --- Quest Events (新增) ---
 
-    pub creator: Signer<'info>,
+#[event]
+pub struct QuestProgressInitialized {
+    pub owner: Pubkey,
 }
 
-#[derive(Accounts)]
-pub struct DisburseFunds<'info> {
-    #[account(
-        mut,
-        seeds = [
-            b"funding_event",
-            funding_event.creator.as_ref(),
-            &funding_event.created_at.to_le_bytes()
-        ],
-        bump = funding_event.bump
-    )]
-    pub funding_event: Account<'info, FundingEvent>,
-
-    #[account(
-        mut,
-        seeds = [
-            b"application",
-            funding_event.key().as_ref(),
-            application.applicant.as_ref()
-        ],
-        bump = application.bump
-    )]
-    pub application: Account<'info, Application>,
+#[event]
+pub struct QuestRewardClaimed {
+    pub owner: Pubkey,
+    pub quest: QuestKind,
+    pub xp_earned: u32,
+    pub streak: u32,
+    pub day: i64,
+}
 
-    /// CHECK: applicant
-    #[account(mut)]
-    pub applicant: AccountInfo<'info>,
+// --- Circle Events (新增) ---
 
-    pub creator: Signer<'info>,
+#[event]
+pub struct CircleCreated {
+    pub circle: Pubkey,
+    pub owner: Pubkey,
+    pub name: String,
+    pub created_at: i64,
 }
 
-#[derive(Accounts)]
-pub struct CloseEvent<'info> {
-    #[account(
-        mut,
-        seeds = [
-            b"funding_event",
-            funding_event.creator.as_ref(),
-            &funding_event.created_at.to_le_bytes()
-        ],
-        bump = funding_event.bump
-    )]
-    pub funding_event: Account<'info, FundingEvent>,
+#[event]
+pub struct CircleInvited {
+    pub circle: Pubkey,
+    pub invited_by: Pubkey,
+    pub invitee: Pubkey,
+}
 
-    #[account(mut)]
-    pub creator: Signer<'info>,
+#[event]
+pub struct CircleJoined {
+    pub circle: Pubkey,
+    pub member: Pubkey,
 }
 
-// --- Group Split Contexts (新增) ---
+#[event]
+pub struct CircleLeft {
+    pub circle: Pubkey,
+    pub member: Pubkey,
+}
 
-#[derive(Accounts)]
-#[instruction(title: String, total_amount: u64, member_count: u8, ipfs_hash: String, timestamp: i64)]
-pub struct CreateGroupSplit<'info> {
-    #[account(
-        init,
-        payer = creator,
-        space = GroupSplit::LEN,
-        seeds = [
-            b"group_split",
-            creator.key().as_ref(),
-            &timestamp.to_le_bytes()
-        ],
-        bump
-    )]
-    pub group_split: Account<'info, GroupSplit>,
+#[event]
+pub struct CircleMemberRemoved {
+    pub circle: Pubkey,
+    pub member: Pubkey,
+    pub removed_by: Pubkey,
+}
 
-    #[account(mut)]
-    pub creator: Signer<'info>,
+#[event]
+pub struct CircleRoleChanged {
+    pub circle: Pubkey,
+    pub member: Pubkey,
+    pub role: MemberRole,
+}
 
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct CircleDissolved {
+    pub circle: Pubkey,
+    pub owner: Pubkey,
 }
 
-#[derive(Accounts)]
-#[instruction(member_pubkey: Pubkey)]
-pub struct AddSplitMember<'info> {
-    #[account(
-        mut,
-        seeds = [
-            b"group_split",
-            group_split.creator.as_ref(),
-            &group_split.created_at.to_le_bytes()
-        ],
-        bump = group_split.bump
-    )]
-    pub group_split: Account<'info, GroupSplit>,
+// --- Group Chat Events (新增) ---
 
-    #[account(
-        init,
-        payer = creator,
-        space = SplitMember::LEN,
-        seeds = [
-            b"split_member",
-            group_split.key().as_ref(),
-            member_pubkey.as_ref()
-        ],
-        bump
-    )]
-    pub split_member: Account<'info, SplitMember>,
+#[event]
+pub struct GroupChatCreated {
+    pub group_chat: Pubkey,
+    pub owner: Pubkey,
+    pub name: String,
+    pub topic: Pubkey,
+}
 
-    #[account(mut)]
-    pub creator: Signer<'info>,
+#[event]
+pub struct GroupChatInvited {
+    pub group_chat: Pubkey,
+    pub invited_by: Pubkey,
+    pub invitee: Pubkey,
+}
 
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct GroupChatJoined {
+    pub group_chat: Pubkey,
+    pub member: Pubkey,
 }
 
-#[derive(Accounts)]
-pub struct MarkSplitPaid<'info> {
-    #[account(
-        mut,
-        seeds = [
-            b"group_split",
-            group_split.creator.as_ref(),
-            &group_split.created_at.to_le_bytes()
-        ],
-        bump = group_split.bump
-    )]
-    pub group_split: Account<'info, GroupSplit>,
+#[event]
+pub struct GroupChatMemberKicked {
+    pub group_chat: Pubkey,
+    pub member: Pubkey,
+    pub kicked_by: Pubkey,
+}
 
-    #[account(
-        mut,
-        seeds = [
-            b"split_member",
-            group_split.key().as_ref(),
-            split_member.member.as_ref()
-        ],
-        bump = split_member.bump
-    )]
-    pub split_member: Account<'info, SplitMember>,
+#[event]
+pub struct GroupChatLeft {
+    pub group_chat: Pubkey,
+    pub member: Pubkey,
+}
 
-    #[account(mut)]
-    pub payer: Signer<'info>,
+#[event]
+pub struct GroupChatRoleChanged {
+    pub group_chat: Pubkey,
+    pub member: Pubkey,
+    pub role: MemberRole,
 }
 
-#[derive(Accounts)]
-pub struct CloseGroupSplit<'info> {
-    #[account(
-        mut,
-        seeds = [
-            b"group_split",
-            group_split.creator.as_ref(),
-            &group_split.created_at.to_le_bytes()
-        ],
-        bump = group_split.bump
-    )]
-    pub group_split: Account<'info, GroupSplit>,
+// --- Migration Events (新增) ---
 
-    #[account(mut)]
-    pub creator: Signer<'info>,
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub version: u8,
 }
 
 // ============================================================================
@@ -1485,4 +8810,275 @@ pub enum ErrorCode {
 
     #[msg("Member already paid")]
     AlreadyPaid,
+
+    // 新增：宠物错误
+    #[msg("Select a pet before hatching it")]
+    PetNotSelected,
+
+    #[msg("Pet was fed recently, try again later")]
+    PetFeedCooldown,
+
+    #[msg("Pet played recently, try again later")]
+    PetPlayCooldown,
+
+    #[msg("Pet is too tired to play")]
+    PetTooTired,
+
+    #[msg("Reward config account is required to reward a pet")]
+    RewardConfigMissing,
+
+    #[msg("Pet has reached its final form")]
+    PetFullyEvolved,
+
+    #[msg("Pet level is too low to evolve")]
+    EvolutionLevelTooLow,
+
+    #[msg("Not enough friends to evolve")]
+    EvolutionNeedsFriends,
+
+    #[msg("Not enough expense records to evolve")]
+    EvolutionNeedsExpenses,
+
+    #[msg("Pet has already been minted as an NFT")]
+    PetAlreadyMinted,
+
+    #[msg("Pet has not been minted as an NFT")]
+    PetNotMinted,
+
+    #[msg("Metadata URI must be 200 characters or less")]
+    InvalidMetadataUri,
+
+    #[msg("Signer already owns this pet")]
+    PetAlreadyOwned,
+
+    #[msg("Signer does not hold the pet NFT")]
+    PetNftNotHeld,
+
+    #[msg("Pet NFT has not been burned")]
+    PetNftNotBurned,
+
+    // 新增：每日任务错误
+    #[msg("Quest is not completed yet")]
+    QuestNotCompleted,
+
+    #[msg("Quest reward already claimed today")]
+    QuestAlreadyClaimed,
+
+    // 新增：用户名注册表错误
+    #[msg("Username is already taken")]
+    UsernameTaken,
+
+    #[msg("Use change_username to pick a different username")]
+    UsernameChangeRequiresRename,
+
+    #[msg("New username is the same as the current one")]
+    UsernameUnchanged,
+
+    // 新增：用户名校验错误
+    #[msg("Username must be at least 3 characters")]
+    UsernameTooShort,
+
+    #[msg("Username must be 20 characters or less")]
+    UsernameTooLong,
+
+    #[msg("Username cannot contain whitespace")]
+    UsernameContainsWhitespace,
+
+    #[msg("Username cannot contain control characters")]
+    UsernameContainsControlCharacter,
+
+    #[msg("Username may only contain letters, digits, '_', '-', '.' and Chinese characters")]
+    UsernameInvalidCharacter,
+
+    #[msg("Username cannot start or end with '_', '-' or '.'")]
+    UsernameSeparatorAtEdge,
+
+    // 新增：注销 Profile 错误
+    #[msg("Remove all friends before closing the profile")]
+    ProfileHasFriends,
+
+    #[msg("Close expense stats before closing the profile")]
+    ProfileHasExpenseStats,
+
+    #[msg("Pet has been minted as an NFT and cannot be closed with the profile")]
+    ProfileHasPetNft,
+
+    #[msg("Delete all expense records first")]
+    ExpenseRecordsRemaining,
+
+    // 新增：Profile 扩展资料错误
+    #[msg("Bio must be 160 characters or less")]
+    BioTooLong,
+
+    #[msg("Bio cannot contain control characters")]
+    BioContainsControlCharacter,
+
+    #[msg("Image URI must be an ipfs://, ar:// or https:// URI of 200 characters or less")]
+    InvalidImageUri,
+
+    #[msg("At most 4 links are allowed")]
+    TooManyLinks,
+
+    #[msg("Links must be https:// URLs of 100 characters or less")]
+    InvalidLink,
+
+    // 新增：账户迁移错误
+    #[msg("Account is not a migratable program account")]
+    InvalidMigrationAccount,
+
+    #[msg("Account is already on the current version")]
+    AccountAlreadyMigrated,
+
+    // 新增：好友请求拒绝/撤回错误
+    #[msg("Friend request is no longer pending")]
+    FriendshipNotPending,
+
+    // 新增：拉黑错误
+    #[msg("This user has been blocked")]
+    UserBlocked,
+
+    #[msg("Cannot block yourself")]
+    CannotBlockSelf,
+
+    #[msg("Removing the friendship requires the requester and both profiles")]
+    BlockMissingFriendshipAccounts,
+
+    // 新增：好友分页错误
+    #[msg("Friend page must be an existing page with room or the next new page")]
+    InvalidFriendPage,
+
+    #[msg("Friend page is full")]
+    FriendPageFull,
+
+    #[msg("Both users' friend index and page accounts are required")]
+    FriendIndexAccountsMissing,
+
+    // 新增：好友圈错误
+    #[msg("Circle name must be between 1 and 32 bytes")]
+    InvalidCircleName,
+
+    #[msg("Circle is full")]
+    CircleFull,
+
+    #[msg("Circle invite list is full")]
+    CircleInviteListFull,
+
+    #[msg("User is already a circle member")]
+    AlreadyCircleMember,
+
+    #[msg("User has already been invited")]
+    AlreadyInvited,
+
+    #[msg("You have not been invited to this circle")]
+    NotInvitedToCircle,
+
+    #[msg("User is not a circle member")]
+    NotCircleMember,
+
+    #[msg("Only the circle owner or an admin can do this")]
+    NotCircleAdmin,
+
+    #[msg("Circle owner cannot leave; dissolve the circle instead")]
+    CircleOwnerCannotLeave,
+
+    #[msg("Circle role can only be switched between Admin and Member")]
+    InvalidCircleRole,
+
+    // 新增：聊天室创建错误
+    #[msg("An accepted friendship is required to open a chat room")]
+    FriendshipRequired,
+
+    // 新增：加密消息错误
+    #[msg("Encryption key must be a new, non-zero X25519 public key")]
+    InvalidEncryptionKey,
+
+    #[msg("Declared key versions do not match the chat members' current keys")]
+    EncryptionKeyVersionMismatch,
+
+    // 新增：群聊错误
+    #[msg("Group chat name must be between 1 and 32 bytes")]
+    InvalidGroupChatName,
+
+    #[msg("Group chat is full")]
+    GroupChatFull,
+
+    #[msg("Group chat invite list is full")]
+    GroupChatInviteListFull,
+
+    #[msg("User is already a group chat member")]
+    AlreadyGroupChatMember,
+
+    #[msg("You have not been invited to this group chat")]
+    NotInvitedToGroupChat,
+
+    #[msg("User is not a group chat member")]
+    NotGroupChatMember,
+
+    #[msg("Only the group chat owner or an admin can do this")]
+    NotGroupChatAdmin,
+
+    #[msg("Group chat owner cannot leave")]
+    GroupChatOwnerCannotLeave,
+
+    #[msg("Group chat role can only be switched between Admin and Member")]
+    InvalidGroupChatRole,
+
+    // 新增：消息编辑错误
+    #[msg("Edit window must not be negative")]
+    InvalidEditWindow,
+
+    #[msg("Message can no longer be edited")]
+    MessageEditWindowExpired,
+
+    #[msg("Encrypted messages cannot be edited")]
+    CannotEditEncryptedMessage,
+
+    // 新增：分页消息错误
+    #[msg("Chat page is full")]
+    ChatPageFull,
+
+    #[msg("Invalid chat page")]
+    InvalidChatPage,
+
+    #[msg("Chat room keeps all pages")]
+    ChatRetentionDisabled,
+
+    #[msg("Chat page is still within the retention window")]
+    ChatPageRetained,
+
+    // 新增：压缩聊天记录错误
+    #[msg("Chat tree is already initialized")]
+    ChatTreeAlreadyInitialized,
+
+    #[msg("Chat tree is not initialized")]
+    ChatTreeNotInitialized,
+
+    #[msg("Invalid chat tree account")]
+    InvalidChatTree,
+
+    // 新增：注销 Profile 错误
+    #[msg("Accept, reject or cancel pending friend requests before closing the profile")]
+    ProfileHasPendingRequests,
+
+    #[msg("Every friend page of the friend index is required")]
+    FriendPagesMissing,
+
+    // 新增：分页消息错误
+    #[msg("Both chat members must sign to shorten chat retention")]
+    ChatRetentionNeedsBothMembers,
+
+    // 新增：群聊错误
+    #[msg("Group chat name cannot contain control characters or leading/trailing spaces")]
+    GroupChatNameInvalidCharacter,
+
+    #[msg("Group chat topic must be a split, funding event or circle of this program")]
+    InvalidGroupChatTopic,
+
+    // 新增：宠物 NFT 错误
+    #[msg("New holder already has a pet; release it or transfer its NFT before syncing")]
+    NewOwnerHasPet,
+
+    // 新增：选择宠物错误
+    #[msg("Release the current pet before selecting a new one")]
+    PetAlreadyAdopted,
 }